@@ -0,0 +1,54 @@
+use go_ai::go::book::{OpeningBook, DEFAULT_MAX_DEPTH};
+use go_ai::go::selfplay::read_binary;
+use go_ai::go::sgf::parse_collection;
+use std::env;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::process;
+
+const USAGE: &str = "Usage: build_book [--sgf FILE]... [--self-play FILE]... [--depth N] --output FILE";
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let mut sgf_files = Vec::new();
+    let mut self_play_files = Vec::new();
+    let mut depth = DEFAULT_MAX_DEPTH;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| fail(format!("Missing value for {}\n{}", arg, USAGE)));
+        match arg.as_str() {
+            "--sgf" => sgf_files.push(value),
+            "--self-play" => self_play_files.push(value),
+            "--depth" => depth = value.parse().unwrap_or_else(|_| fail(format!("Invalid depth: {}", value))),
+            "--output" => output = Some(value),
+            _ => fail(format!("Unknown option: {}\n{}", arg, USAGE)),
+        }
+    }
+    let output = output.unwrap_or_else(|| fail(USAGE.to_string()));
+
+    let mut book = OpeningBook::new(depth);
+    let mut skipped = 0;
+    for path in &sgf_files {
+        let text = fs::read_to_string(path).unwrap_or_else(|error| fail(format!("Can not read {}: {}", path, error)));
+        let games = parse_collection(&text).unwrap_or_else(|_| fail(format!("{} is not a valid SGF file", path)));
+        for game in &games {
+            if book.add_sgf(game).is_err() {
+                skipped += 1;
+            }
+        }
+    }
+    for path in &self_play_files {
+        let file = File::open(path).unwrap_or_else(|error| fail(format!("Can not open {}: {}", path, error)));
+        let records = read_binary(BufReader::new(file)).unwrap_or_else(|_| fail(format!("{} is not a binary self-play dataset", path)));
+        book.add_self_play(&records);
+    }
+
+    book.save_file(&output).unwrap_or_else(|error| fail(format!("Can not write {}: {}", output, error)));
+    eprintln!("Wrote {} positions to {}, skipped {} games", book.len(), output, skipped);
+}
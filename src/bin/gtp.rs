@@ -0,0 +1,46 @@
+use go_ai::go::ai::{available_ai_names, create_ai};
+use go_ai::go::gtp::GtpEngine;
+use go_ai::go::scoring::DEFAULT_KOMI;
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+use std::time::SystemTime;
+
+const USAGE: &str = "Usage: gtp [--ai NAME]";
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let mut name = "mcts".to_string();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| fail(format!("Missing value for {}\n{}", arg, USAGE)));
+        match arg.as_str() {
+            "--ai" => name = value,
+            _ => fail(format!("Unknown option: {}\n{}", arg, USAGE)),
+        }
+    }
+
+    let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    let mut ai = create_ai(&name, seed)
+        .unwrap_or_else(|| fail(format!("Can not create the {} AI, expected one of {}", name, available_ai_names().join(", "))));
+    ai.set_komi(DEFAULT_KOMI);
+    let mut engine = GtpEngine::new(ai);
+
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.unwrap_or_else(|error| fail(format!("Can not read a command: {}", error)));
+        if let Some(response) = engine.execute(&line) {
+            output.write_all(response.as_bytes()).and_then(|_| output.flush())
+                .unwrap_or_else(|error| fail(format!("Can not write a response: {}", error)));
+        }
+        if engine.has_quit() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use go_ai::go::pattern::{competitions_from_sgf, PatternDatabase, DEFAULT_ITERATIONS};
+use go_ai::go::sgf::parse_collection;
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: learn_patterns --sgf FILE... [--iterations N] --output FILE";
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let mut sgf_files = Vec::new();
+    let mut iterations = DEFAULT_ITERATIONS;
+    let mut output = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| fail(format!("Missing value for {}\n{}", arg, USAGE)));
+        match arg.as_str() {
+            "--sgf" => sgf_files.push(value),
+            "--iterations" => iterations = value.parse().unwrap_or_else(|_| fail(format!("Invalid iterations: {}", value))),
+            "--output" => output = Some(value),
+            _ => fail(format!("Unknown option: {}\n{}", arg, USAGE)),
+        }
+    }
+    let output = output.unwrap_or_else(|| fail(USAGE.to_string()));
+
+    let mut games = Vec::new();
+    for path in &sgf_files {
+        let text = fs::read_to_string(path).unwrap_or_else(|error| fail(format!("Can not read {}: {}", path, error)));
+        games.extend(parse_collection(&text).unwrap_or_else(|_| fail(format!("{} is not a valid SGF file", path))));
+    }
+
+    let competitions = competitions_from_sgf(&games);
+    eprintln!("{} games, {} moves", games.len(), competitions.len());
+    let database = PatternDatabase::learn(&competitions, iterations);
+
+    database.save_file(&output).unwrap_or_else(|error| fail(format!("Can not write {}: {}", output, error)));
+    eprintln!("Wrote {} patterns to {}", database.len(), output);
+}
@@ -0,0 +1,105 @@
+use go_ai::go::ai::GoAI;
+use go_ai::go::ai::mcts_ai::MctsAI;
+use go_ai::go::board::GoStone;
+use go_ai::go::pattern::PatternDatabase;
+use go_ai::go::playout::heavy::{HeavyPolicy, PlayoutRule};
+use go_ai::go::playout::{run_playout, PlayoutPolicy, PlayoutState, RandomPolicy};
+use go_ai::go::scoring::DEFAULT_KOMI;
+use go_ai::go::selfplay::game_winner;
+use go_ai::go::GoGame;
+use rand_pcg::Pcg64Mcg;
+use rand::SeedableRng;
+use std::env;
+use std::process;
+use std::sync::Arc;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: playout_bench [--playouts N] [--games N] [--search-playouts N] [--patterns FILE] [--seed S]";
+const MAX_MOVES: usize = 3 * 9 * 9;
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn playouts_per_second<P: PlayoutPolicy>(policy: &P, playouts: usize, seed: u64) -> f64 {
+    let mut policy = policy.clone();
+    let mut random = Pcg64Mcg::seed_from_u64(seed);
+    let start = Instant::now();
+    for _ in 0..playouts {
+        run_playout(PlayoutState::from_game(&GoGame::new()), &mut policy, &mut random);
+    }
+    playouts as f64 / start.elapsed().as_secs_f64()
+}
+
+/// The share of games that MCTS with `policy` wins against MCTS with uniform random playouts.
+fn win_rate<P: PlayoutPolicy>(policy: &P, games: u64, search_playouts: usize, seed: u64) -> f32 {
+    let mut wins = 0.0;
+    for index in 0..games {
+        let mut tested = MctsAI::with_policy(policy.clone(), seed + index, search_playouts);
+        let mut baseline = MctsAI::with_seed(seed + index, search_playouts);
+        let tested_color = if index % 2 == 0 {GoStone::BLACK} else {GoStone::WHITE};
+
+        let mut game = GoGame::new();
+        while game.consecutive_passes() < 2 && game.moves().len() < MAX_MOVES {
+            let ai: &mut dyn GoAI = if game.current_turn() == tested_color {&mut tested} else {&mut baseline};
+            ai.set_game(game.clone());
+            let game_move = ai.best_move().filter(|&coordinates| game.is_legal(coordinates));
+            game.play(game_move).unwrap();
+        }
+
+        let winner = game_winner(&game, DEFAULT_KOMI, seed + index);
+        if winner == tested_color {
+            wins += 1.0;
+        } else if winner.is_none() {
+            wins += 0.5;
+        }
+    }
+    wins / games.max(1) as f32
+}
+
+fn report<P: PlayoutPolicy>(name: &str, policy: &P, playouts: usize, games: u64, search_playouts: usize, seed: u64) {
+    let speed = playouts_per_second(policy, playouts, seed);
+    if games > 0 {
+        println!("{:<26} {:>10.0} playouts/s {:>8.1}% against random", name, speed, 100.0 * win_rate(policy, games, search_playouts, seed));
+    } else {
+        println!("{:<26} {:>10.0} playouts/s", name, speed);
+    }
+}
+
+/// Compares uniform random playouts, the heavy policy with all rules and the heavy policy
+/// without each single rule.
+fn main() {
+    let mut playouts: usize = 1000;
+    let mut games: u64 = 0;
+    let mut search_playouts: usize = 500;
+    let mut patterns = None;
+    let mut seed: u64 = 0;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| fail(format!("Missing value for {}\n{}", arg, USAGE)));
+        let invalid = format!("Invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--playouts" => playouts = value.parse().unwrap_or_else(|_| fail(invalid.clone())),
+            "--games" => games = value.parse().unwrap_or_else(|_| fail(invalid.clone())),
+            "--search-playouts" => search_playouts = value.parse().unwrap_or_else(|_| fail(invalid.clone())),
+            "--seed" => seed = value.parse().unwrap_or_else(|_| fail(invalid.clone())),
+            "--patterns" => patterns = Some(Arc::new(PatternDatabase::load_file(&value).unwrap_or_else(|_| fail(invalid.clone())))),
+            _ => fail(format!("Unknown option: {}\n{}", arg, USAGE)),
+        }
+    }
+
+    let heavy = match patterns {
+        Some(patterns) => HeavyPolicy::with_patterns(patterns),
+        None => HeavyPolicy::new(),
+    };
+
+    report("random", &RandomPolicy, playouts, 0, search_playouts, seed);
+    report("heavy", &heavy, playouts, games, search_playouts, seed);
+    for &rule in &PlayoutRule::ALL {
+        let mut policy = heavy.clone();
+        policy.set_enabled(rule, false);
+        report(&format!("without {}", rule.name()), &policy, playouts, games, search_playouts, seed);
+    }
+}
@@ -0,0 +1,106 @@
+use go_ai::go::ai::{create_ai, AI_NAMES, PUCT_AI};
+use go_ai::go::ai::mcts_ai::MctsAI;
+use go_ai::go::ai::GoAI;
+use go_ai::go::ai::puct_ai::{PuctAI, DEFAULT_SAMPLING_MOVES, DEFAULT_VISITS};
+use go_ai::go::nn::{Network, DEFAULT_BLOCKS, DEFAULT_CHANNELS, DEFAULT_VALUE_HIDDEN};
+use go_ai::go::scoring::DEFAULT_KOMI;
+use go_ai::go::selfplay::{play_game, DatasetFormat, DatasetWriter};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process;
+
+const MAX_MOVES: usize = 3 * 9 * 9;
+
+const USAGE: &str = "Usage: self_play [--games N] [--seed S] [--ai NAME] [--playouts N] [--weights FILE] [--komi K] [--format binary|jsonl] [--output FILE]";
+
+struct Options {
+    games: u64,
+    seed: u64,
+    ai: String,
+    playouts: Option<usize>,
+    weights: Option<Network>,
+    komi: f32,
+    format: DatasetFormat,
+    output: Option<String>,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {games: 1, seed: 0, ai: "mcts".to_string(), playouts: None, weights: None, komi: DEFAULT_KOMI, format: DatasetFormat::Binary, output: None};
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        let invalid = |_| format!("Invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--games" => options.games = value.parse().map_err(invalid)?,
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            "--playouts" => options.playouts = Some(value.parse().map_err(invalid)?),
+            "--komi" => options.komi = value.parse().map_err(|_| format!("Invalid value for {}: {}", arg, value))?,
+            "--weights" => options.weights = Some(Network::load_file(&value).map_err(|_| format!("Can not load weights from {}", value))?),
+            "--ai" => options.ai = value,
+            "--format" => options.format = match value.as_str() {
+                "binary" => DatasetFormat::Binary,
+                "jsonl" => DatasetFormat::Jsonl,
+                _ => return Err(format!("Unknown format: {}", value)),
+            },
+            "--output" => options.output = Some(value),
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    if options.ai != PUCT_AI && !AI_NAMES.contains(&options.ai.as_str()) {
+        return Err(format!("Unknown AI: {}, expected one of {}, {}", options.ai, AI_NAMES.join(", "), PUCT_AI));
+    }
+    if options.playouts.is_some() && options.ai != "mcts" && options.ai != PUCT_AI {
+        return Err(format!("--playouts has no effect on the {} AI", options.ai));
+    }
+    if options.weights.is_some() && options.ai != PUCT_AI {
+        return Err(format!("--weights has no effect on the {} AI", options.ai));
+    }
+    Ok(options)
+}
+
+fn create(options: &Options, seed: u64) -> Box<dyn GoAI + Send> {
+    let mut ai: Box<dyn GoAI + Send> = match (options.ai.as_str(), options.playouts) {
+        (PUCT_AI, playouts) => {
+            let network = options.weights.clone().unwrap_or_else(|| Network::new(DEFAULT_CHANNELS, DEFAULT_BLOCKS, DEFAULT_VALUE_HIDDEN, seed));
+            let mut ai = PuctAI::with_visits(network, playouts.unwrap_or(DEFAULT_VISITS));
+            ai.set_exploration(seed, DEFAULT_SAMPLING_MOVES);
+            Box::new(ai)
+        }
+        ("mcts", Some(playouts)) => Box::new(MctsAI::with_seed(seed, playouts)),
+        (name, _) => create_ai(name, seed).unwrap(),
+    };
+    ai.set_komi(options.komi);
+    ai
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|message| {
+        eprintln!("{}\n{}", message, USAGE);
+        process::exit(2);
+    });
+
+    let output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(File::create(path).unwrap_or_else(|error| {
+            eprintln!("Can not create {}: {}", path, error);
+            process::exit(1);
+        })),
+        None => Box::new(io::stdout()),
+    };
+    let mut writer = DatasetWriter::new(BufWriter::new(output), options.format).unwrap();
+
+    for game in 0..options.games {
+        let seed = options.seed.wrapping_add(game);
+        let mut ai = create(&options, seed);
+        let records = play_game(ai.as_mut(), options.komi, MAX_MOVES, seed);
+
+        for record in &records {
+            writer.write(record).unwrap();
+        }
+        eprintln!("Game {}: {} moves, winner {:?}", game + 1, records.len(), records.last().map(|record| record.winner()));
+    }
+
+    writer.into_inner().flush().unwrap();
+}
@@ -0,0 +1,117 @@
+use go_ai::go::nn::{Network, DEFAULT_BLOCKS, DEFAULT_CHANNELS, DEFAULT_VALUE_HIDDEN};
+use go_ai::go::nn::training::{gating_match, Optimizer, Trainer, TrainingSample};
+use go_ai::go::scoring::DEFAULT_KOMI;
+use go_ai::go::selfplay::read_binary;
+use rand_pcg::Pcg64Mcg;
+use rand::SeedableRng;
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::process;
+
+const USAGE: &str = "Usage: train --data FILE... --output FILE [--weights FILE] [--epochs N] [--batch-size N] \
+[--optimizer sgd|adam] [--learning-rate R] [--gating-games N] [--gating-visits N] [--seed S]";
+
+struct Options {
+    data: Vec<String>,
+    weights: Option<String>,
+    output: Option<String>,
+    epochs: usize,
+    batch_size: usize,
+    adam: bool,
+    learning_rate: Option<f32>,
+    gating_games: u32,
+    gating_visits: usize,
+    seed: u64,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        data: Vec::new(), weights: None, output: None, epochs: 1, batch_size: 32, adam: true,
+        learning_rate: None, gating_games: 10, gating_visits: 50, seed: 0,
+    };
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        let invalid = format!("Invalid value for {}: {}", arg, value);
+        match arg.as_str() {
+            "--data" => options.data.push(value),
+            "--weights" => options.weights = Some(value),
+            "--output" => options.output = Some(value),
+            "--epochs" => options.epochs = value.parse().map_err(|_| invalid)?,
+            "--batch-size" => options.batch_size = value.parse().map_err(|_| invalid)?,
+            "--learning-rate" => options.learning_rate = Some(value.parse().map_err(|_| invalid)?),
+            "--gating-games" => options.gating_games = value.parse().map_err(|_| invalid)?,
+            "--gating-visits" => options.gating_visits = value.parse().map_err(|_| invalid)?,
+            "--seed" => options.seed = value.parse().map_err(|_| invalid)?,
+            "--optimizer" => options.adam = match value.as_str() {
+                "adam" => true,
+                "sgd" => false,
+                _ => return Err(invalid),
+            },
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    if options.data.is_empty() || options.output.is_none() {
+        return Err("--data and --output are required".to_string());
+    }
+    Ok(options)
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+/// Trains a candidate on the self-play data and writes it to the output if it wins the gating match
+/// against the current weights. Without current weights the candidate is always written.
+fn main() {
+    let options = parse_options().unwrap_or_else(|message| {
+        eprintln!("{}\n{}", message, USAGE);
+        process::exit(2);
+    });
+
+    let mut samples = Vec::new();
+    for path in &options.data {
+        let file = File::open(path).unwrap_or_else(|error| fail(format!("Can not open {}: {}", path, error)));
+        let records = read_binary(BufReader::new(file)).unwrap_or_else(|_| fail(format!("{} is not a binary self-play dataset", path)));
+        samples.extend(records.iter().map(TrainingSample::from_record));
+    }
+    if samples.is_empty() {
+        fail("No training samples".to_string());
+    }
+
+    let best = options.weights.as_ref().map(|path| {
+        Network::load_file(path).unwrap_or_else(|_| fail(format!("Can not load weights from {}", path)))
+    });
+    let initial = best.clone().unwrap_or_else(|| Network::new(DEFAULT_CHANNELS, DEFAULT_BLOCKS, DEFAULT_VALUE_HIDDEN, options.seed));
+
+    let optimizer = if options.adam {
+        Optimizer::adam(options.learning_rate.unwrap_or(1e-3))
+    } else {
+        Optimizer::sgd(options.learning_rate.unwrap_or(1e-2))
+    };
+    let mut trainer = Trainer::new(initial, optimizer);
+    let mut random = Pcg64Mcg::seed_from_u64(options.seed);
+
+    for epoch in 0..options.epochs {
+        let loss = trainer.train_epoch(&samples, options.batch_size, &mut random);
+        eprintln!("Epoch {}: loss {:.4}", epoch + 1, loss);
+    }
+
+    let output = options.output.unwrap();
+    let candidate = trainer.into_network();
+    if let Some(best) = best {
+        let result = gating_match(&candidate, &best, options.gating_games, options.gating_visits, DEFAULT_KOMI, options.seed);
+        eprintln!("Gating: {} wins, {} losses, {} draws", result.wins, result.losses, result.draws);
+        if !result.promotes() {
+            eprintln!("Candidate rejected, keeping the current weights");
+            return;
+        }
+    }
+
+    candidate.save_file(&output).unwrap_or_else(|error| fail(format!("Can not write {}: {}", output, error)));
+    eprintln!("Wrote {}", output);
+}
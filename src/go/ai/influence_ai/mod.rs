@@ -0,0 +1,42 @@
+use crate::go::board::GoCoordinates;
+use crate::go::board::GoStone;
+use crate::go::GoGame;
+use crate::go::ai::{GoAI, choose_by_evaluation};
+use rand_pcg::Pcg64Mcg;
+use rand::SeedableRng;
+use std::time::SystemTime;
+
+pub struct InfluenceAI {
+    game: GoGame,
+    random: Pcg64Mcg,
+}
+
+impl InfluenceAI {
+    pub fn new() -> Self {
+        Self::with_seed(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {game: GoGame::new(), random: SeedableRng::seed_from_u64(seed)}
+    }
+}
+
+impl Default for InfluenceAI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GoAI for InfluenceAI {
+    fn set_game(&mut self, game: GoGame) {
+        self.game = game;
+    }
+
+    fn best_move(&mut self) -> Option<GoCoordinates> {
+        choose_by_evaluation(&mut self.game, &mut self.random, |board, color| {
+            let (black_score, white_score) = board.influence_score();
+            let diff = white_score as i64 - black_score as i64;
+            if color == GoStone::BLACK {-diff} else {diff}
+        })
+    }
+}
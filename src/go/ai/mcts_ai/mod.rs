@@ -0,0 +1,448 @@
+use crate::go::board::{GoCoordinates, GoStone};
+use crate::go::board::analysis::eyes::is_true_eye;
+use crate::go::board::zobrist::{self, splitmix64};
+use crate::go::GoGame;
+use crate::go::ai::{GoAI, MoveAnalysis};
+use crate::go::ai::transposition::TranspositionTable;
+use crate::go::clock::PlayerClock;
+use crate::go::playout::{PlayoutPolicy, PlayoutState, RandomPolicy, run_playout};
+use crate::go::scoring::DEFAULT_KOMI;
+use rand_pcg::Pcg64Mcg;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::mem;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_PLAYOUTS: usize = 1000;
+pub const EXPLORATION: f32 = 1.4;
+/// Pondering stops by itself after this many playouts in the tree, to bound its memory.
+pub const MAX_PONDER_PLAYOUTS: u32 = 100_000;
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+/// Monte Carlo tree search with UCT selection and playouts of policy `P`.
+/// The tree is kept between moves: when the new game continues the old one, the subtree
+/// of the moves played since is reused. The playouts through each position are also added
+/// to a transposition table, so nodes of the same position reached by other move orders
+/// select on their combined win rate.
+pub struct MctsAI<P: PlayoutPolicy = RandomPolicy> {
+    search: Search<P>,
+    playouts: usize,
+    time_budget: Option<Duration>,
+    pondering: Option<(Arc<AtomicBool>, JoinHandle<Search<P>>)>,
+}
+
+/// Everything a search needs, so it can move to the pondering thread and back.
+struct Search<P: PlayoutPolicy> {
+    game: GoGame,
+    policy: P,
+    random: Pcg64Mcg,
+    komi: f32,
+    root: Node,
+    table: Arc<TranspositionTable>,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    game_move: Option<GoCoordinates>,
+    player: GoStone,
+    /// The table key of the position after `game_move`.
+    key: u64,
+    visits: u32,
+    wins: f32,
+    /// Summed final score margin of `player`, komi included.
+    score_sum: f32,
+    children: Vec<Node>,
+    untried: Vec<Option<GoCoordinates>>,
+}
+
+impl MctsAI {
+    pub fn new() -> Self {
+        Self::with_seed(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs(), DEFAULT_PLAYOUTS)
+    }
+
+    pub fn with_seed(seed: u64, playouts: usize) -> Self {
+        Self::with_policy(RandomPolicy, seed, playouts)
+    }
+}
+
+impl Default for MctsAI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: PlayoutPolicy> MctsAI<P> {
+    pub fn with_policy(policy: P, seed: u64, playouts: usize) -> Self {
+        let game = GoGame::new();
+        let root = Node::root(&game, table_key(&game, DEFAULT_KOMI));
+        let table = Arc::new(TranspositionTable::new(DEFAULT_TABLE_SIZE));
+        let search = Search {game, policy, random: SeedableRng::seed_from_u64(seed), komi: DEFAULT_KOMI, root, table};
+        Self {search, playouts, time_budget: None, pondering: None}
+    }
+
+    pub fn set_komi(&mut self, komi: f32) {
+        self.stop_pondering();
+        self.search.komi = komi;
+        self.search.root = Node::root(&self.search.game, table_key(&self.search.game, komi));
+    }
+
+    /// Shares `table` with other searches. The keys include the komi, so searches with different komi do not mix.
+    pub fn set_table(&mut self, table: Arc<TranspositionTable>) {
+        self.stop_pondering();
+        self.search.table = table;
+    }
+
+    /// The estimated probability that the side to move wins, from the last search.
+    pub fn win_rate(&self) -> f32 {
+        let root = &self.search.root;
+        if root.visits == 0 {
+            0.5
+        } else {
+            1.0 - root.wins / root.visits as f32
+        }
+    }
+
+    /// The number of playouts in the current tree, including reused and pondered ones.
+    pub fn tree_playouts(&self) -> u32 {
+        self.search.root.visits
+    }
+}
+
+impl<P: PlayoutPolicy> Search<P> {
+    /// The area score margin of black at the end of a playout from `game`, komi included.
+    fn margin(&self, game: &GoGame, random: &mut Pcg64Mcg) -> f32 {
+        let state = if game.consecutive_passes() >= 2 {
+            PlayoutState::from_game(game)
+        } else {
+            run_playout(PlayoutState::from_game(game), &mut self.policy.clone(), random)
+        };
+
+        let (black, white) = state.area_score();
+        black as f32 - white as f32 - self.komi
+    }
+
+    /// Returns the margin of black at the end of the playout.
+    fn iterate(&self, node: &mut Node, game: &mut GoGame, random: &mut Pcg64Mcg) -> f32 {
+        let margin = if game.is_over() || game.consecutive_passes() >= 2 {
+            self.margin(game, random)
+        } else if !node.untried.is_empty() {
+            let index = random.gen_range(0..node.untried.len());
+            let game_move = node.untried.swap_remove(index);
+            let player = game.current_turn();
+            game.play(game_move).unwrap();
+
+            let mut child = Node::new(game_move, player, game, table_key(game, self.komi));
+            let margin = self.margin(game, random);
+            self.update(&mut child, margin);
+            node.children.push(child);
+            margin
+        } else if node.children.is_empty() {
+            self.margin(game, random)
+        } else {
+            let child = node.select_child(&self.table);
+            game.play(child.game_move).unwrap();
+            self.iterate(child, game, random)
+        };
+
+        self.update(node, margin);
+        margin
+    }
+
+    /// Counts a playout through `node` in the node and in the table.
+    fn update(&self, node: &mut Node, margin: f32) {
+        let won = node.update(margin);
+        self.table.add_visits(node.key, 1, if won {1.0} else {0.0});
+    }
+
+    /// Runs `playouts` playouts, or stops earlier at `deadline`.
+    fn run(&mut self, playouts: usize, deadline: Option<Instant>) {
+        let mut root = mem::replace(&mut self.root, Node::empty());
+        let mut random = self.random.clone();
+        for _ in 0..playouts {
+            let mut game = self.game.clone();
+            self.iterate(&mut root, &mut game, &mut random);
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+        }
+        self.random = random;
+        self.root = root;
+    }
+
+    /// Moves the root down the moves that lead from the current game to `game`, or starts a new tree.
+    fn advance(&mut self, game: GoGame) {
+        let known = self.game.history().len();
+        let root = mem::replace(&mut self.root, Node::empty());
+
+        self.root = if !game.is_over() && game.history().starts_with(self.game.history()) {
+            game.moves()[known - 1..].iter().try_fold(root, |node, &game_move| {
+                node.children.into_iter().find(|child| child.game_move == game_move)
+            })
+        } else {
+            None
+        }.unwrap_or_else(|| Node::root(&game, table_key(&game, self.komi)));
+        self.game = game;
+    }
+}
+
+impl Node {
+    fn new(game_move: Option<GoCoordinates>, player: GoStone, game: &GoGame, key: u64) -> Self {
+        Self {game_move, player, key, visits: 0, wins: 0.0, score_sum: 0.0, children: Vec::new(), untried: candidate_moves(game)}
+    }
+
+    fn root(game: &GoGame, key: u64) -> Self {
+        Self::new(None, game.current_turn().opponent_color(), game, key)
+    }
+
+    /// A placeholder while the real root is taken out of the search.
+    fn empty() -> Self {
+        Self {game_move: None, player: GoStone::NONE, key: 0, visits: 0, wins: 0.0, score_sum: 0.0, children: Vec::new(), untried: Vec::new()}
+    }
+
+    /// Counts a playout that ended with `margin` for black, where a draw is a win for white.
+    /// Returns whether `player` won it.
+    fn update(&mut self, margin: f32) -> bool {
+        self.visits += 1;
+        self.score_sum += if self.player == GoStone::BLACK {margin} else {-margin};
+        let winner = if margin > 0.0 {GoStone::BLACK} else {GoStone::WHITE};
+        if winner == self.player {
+            self.wins += 1.0;
+        }
+        winner == self.player
+    }
+
+    /// The most visited line of play from this node.
+    fn variation(&self) -> Vec<Option<GoCoordinates>> {
+        let mut result = vec![self.game_move];
+        let mut node = self;
+        while let Some(child) = node.children.iter().max_by_key(|child| child.visits) {
+            result.push(child.game_move);
+            node = child;
+        }
+        result
+    }
+
+    /// Selects by UCT, with the win rate of all playouts through the child's position in `table`.
+    fn select_child(&mut self, table: &TranspositionTable) -> &mut Node {
+        let log_visits = (self.visits as f32).ln();
+        let mut best_index = 0;
+        let mut best_value = f32::NEG_INFINITY;
+
+        for (index, child) in self.children.iter().enumerate() {
+            let visits = child.visits as f32;
+            let win_rate = match table.get(child.key) {
+                Some(entry) if entry.visits >= child.visits => entry.wins / entry.visits as f32,
+                _ => child.wins / visits,
+            };
+            let value = win_rate + EXPLORATION * (log_visits / visits).sqrt();
+            if value > best_value {
+                best_value = value;
+                best_index = index;
+            }
+        }
+
+        &mut self.children[best_index]
+    }
+}
+
+/// The table key of the current position of `game`, for searches with `komi`.
+fn table_key(game: &GoGame, komi: f32) -> u64 {
+    zobrist::hash(game.current_board(), game.current_turn()) ^ splitmix64(komi.to_bits() as u64)
+}
+
+/// Legal moves that do not fill an own true eye, and passing, or none once the game is over.
+fn candidate_moves(game: &GoGame) -> Vec<Option<GoCoordinates>> {
+    if game.is_over() {
+        return Vec::new();
+    }
+    let color = game.current_turn();
+    let mut result: Vec<_> = (0..9 * 9).map(GoCoordinates::from)
+        .filter(|&c| !is_true_eye(game.current_board(), c, color) && game.is_legal(c))
+        .map(Some)
+        .collect();
+    result.push(None);
+    result
+}
+
+impl<P: PlayoutPolicy> GoAI for MctsAI<P> {
+    fn set_game(&mut self, game: GoGame) {
+        self.stop_pondering();
+        self.search.advance(game);
+    }
+
+    /// Adds `playouts` playouts to the tree, on top of those reused from earlier moves.
+    /// With a clock, it keeps searching until the time budget of the move is used up instead.
+    fn best_move(&mut self) -> Option<GoCoordinates> {
+        self.stop_pondering();
+        match self.time_budget {
+            Some(budget) => self.search.run(usize::MAX, Some(Instant::now() + budget)),
+            None => self.search.run(self.playouts, None),
+        }
+        self.search.root.children.iter().max_by_key(|child| child.visits).and_then(|child| child.game_move)
+    }
+
+    fn visit_counts(&self) -> Vec<(Option<GoCoordinates>, u32)> {
+        self.search.root.children.iter().map(|child| (child.game_move, child.visits)).collect()
+    }
+
+    fn start_pondering(&mut self) {
+        if self.pondering.is_some() {
+            return;
+        }
+
+        let placeholder = Search {
+            game: self.search.game.clone(), policy: self.search.policy.clone(), random: self.search.random.clone(), komi: self.search.komi,
+            root: Node::empty(), table: self.search.table.clone(),
+        };
+        let mut search = mem::replace(&mut self.search, placeholder);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let handle = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) && search.root.visits < MAX_PONDER_PLAYOUTS {
+                search.run(1, None);
+            }
+            search
+        });
+        self.pondering = Some((stop, handle));
+    }
+
+    fn stop_pondering(&mut self) {
+        if let Some((stop, handle)) = self.pondering.take() {
+            stop.store(true, Ordering::Relaxed);
+            self.search = handle.join().unwrap();
+        }
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    fn set_time_left(&mut self, clock: &PlayerClock) {
+        self.time_budget = clock.move_budget();
+    }
+
+    fn win_rate_estimate(&self) -> Option<f32> {
+        Some(self.win_rate())
+    }
+
+    fn set_komi(&mut self, komi: f32) {
+        MctsAI::set_komi(self, komi);
+    }
+
+    fn analysis(&self) -> Vec<MoveAnalysis> {
+        // Reversed before the stable sort so ties are ordered like `max_by_key` in `best_move`.
+        let mut children: Vec<_> = self.search.root.children.iter().rev().filter(|child| child.visits > 0).collect();
+        children.sort_by_key(|child| Reverse(child.visits));
+        children.into_iter().map(|child| MoveAnalysis {
+            game_move: child.game_move,
+            visits: child.visits,
+            win_rate: child.wins / child.visits as f32,
+            score_lead: Some(child.score_sum / child.visits as f32),
+            variation: child.variation(),
+        }).collect()
+    }
+}
+
+impl<P: PlayoutPolicy> Drop for MctsAI<P> {
+    fn drop(&mut self) {
+        self.stop_pondering();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{table_key, MctsAI};
+    use crate::go::ai::GoAI;
+    use crate::go::ai::transposition::TranspositionTable;
+    use crate::go::board::{GoCoordinates, GoStone};
+    use crate::go::scoring::DEFAULT_KOMI;
+    use crate::go::GoGame;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_pondering_reuses_the_subtree() {
+        let mut ai = MctsAI::with_seed(7, 50);
+        let mut game = GoGame::new();
+        ai.set_game(game.clone());
+
+        ai.start_pondering();
+        assert!(ai.is_pondering());
+        thread::sleep(Duration::from_millis(200));
+        ai.stop_pondering();
+        assert!(!ai.is_pondering());
+
+        let pondered = ai.tree_playouts();
+        assert!(pondered > 0);
+        let (reply, visits) = ai.visit_counts().into_iter().max_by_key(|&(_, visits)| visits).unwrap();
+
+        game.play(reply).unwrap();
+        ai.set_game(game.clone());
+        assert_eq!(ai.tree_playouts(), visits);
+
+        ai.best_move();
+        assert_eq!(ai.tree_playouts(), visits + 50);
+
+        game.undo().unwrap();
+        ai.set_game(game);
+        assert_eq!(ai.tree_playouts(), 0);
+    }
+
+    #[test]
+    fn test_analysis() {
+        let mut ai = MctsAI::with_seed(3, 200);
+        ai.set_game(GoGame::new());
+        assert!(ai.analysis().is_empty());
+
+        let best_move = ai.best_move();
+        let analysis = ai.analysis();
+        assert_eq!(analysis[0].game_move, best_move);
+        assert_eq!(analysis[0].variation[0], best_move);
+        assert!(analysis.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        assert_eq!(analysis.iter().map(|candidate| candidate.visits).sum::<u32>(), 200);
+        assert!(analysis.iter().all(|candidate| (0.0..=1.0).contains(&candidate.win_rate) && candidate.score_lead.is_some()));
+    }
+
+    #[test]
+    fn test_transpositions_share_statistics() {
+        let table = Arc::new(TranspositionTable::new(1 << 12));
+        let mut ai = MctsAI::with_seed(9, 300);
+        ai.set_table(table.clone());
+        let game = GoGame::new();
+        ai.set_game(game.clone());
+        ai.best_move();
+        assert_eq!(table.get(table_key(&game, DEFAULT_KOMI)).unwrap().visits, 300);
+
+        let mut first = game.clone();
+        let mut second = game.clone();
+        for &game_move in &[(2, 2), (6, 6), (2, 6)] {
+            first.play_stone(GoCoordinates::new(game_move.0, game_move.1)).unwrap();
+        }
+        for &game_move in &[(2, 6), (6, 6), (2, 2)] {
+            second.play_stone(GoCoordinates::new(game_move.0, game_move.1)).unwrap();
+        }
+        ai.set_game(first);
+        ai.best_move();
+        ai.set_game(second.clone());
+        assert_eq!(ai.tree_playouts(), 0);
+        ai.best_move();
+        assert_eq!(table.get(table_key(&second, DEFAULT_KOMI)).unwrap().visits, 600);
+    }
+
+    #[test]
+    fn test_finished_game() {
+        let mut ai = MctsAI::with_seed(5, 20);
+        let mut game = GoGame::new();
+        ai.set_game(game.clone());
+        ai.best_move();
+        game.resign(GoStone::BLACK);
+        ai.set_game(game);
+        assert_eq!(ai.best_move(), None);
+        assert!(ai.analysis().is_empty());
+    }
+}
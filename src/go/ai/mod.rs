@@ -1,16 +1,263 @@
-use crate::go::board::GoCoordinates;
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+use crate::go::board::analysis::benson::unconditional_life;
+use crate::go::board::analysis::chains;
+use crate::go::board::analysis::ladder::{is_ladder_captured, ladder_capture};
+use crate::go::board::analysis::eyes::is_true_eye;
+use crate::go::clock::PlayerClock;
 use crate::go::GoGame;
+use crate::go::nn::Network;
 use rand_pcg::Pcg64Mcg;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
 use rand::distributions::{Distribution, Uniform};
+use std::io;
+use std::sync::OnceLock;
 use std::time::SystemTime;
 
 pub mod voronoi_ai;
+pub mod influence_ai;
+pub mod mcts_ai;
+pub mod puct_ai;
+pub mod transposition;
+
+/// A candidate move of the last search, as shown when analysing a game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveAnalysis {
+    pub game_move: Option<GoCoordinates>,
+    pub visits: u32,
+    /// The probability that the player to move wins after this move.
+    pub win_rate: f32,
+    /// The expected lead in points of the player to move after this move, komi included, for AIs that estimate it.
+    pub score_lead: Option<f32>,
+    /// The moves the search expects, starting with `game_move`.
+    pub variation: Vec<Option<GoCoordinates>>,
+}
 
 pub trait GoAI {
     fn set_game(&mut self, game: GoGame);
 
+    /// The move to play, where `None` passes.
     fn best_move(&mut self) -> Option<GoCoordinates>;
+
+    /// How often the last search visited each candidate move. Empty for AIs that do not search a tree.
+    fn visit_counts(&self) -> Vec<(Option<GoCoordinates>, u32)> {
+        Vec::new()
+    }
+
+    /// Keeps searching the current game in the background while the opponent thinks.
+    /// `set_game` and `best_move` stop it and keep what was found. Does nothing by default.
+    fn start_pondering(&mut self) {}
+
+    fn stop_pondering(&mut self) {}
+
+    fn is_pondering(&self) -> bool {
+        false
+    }
+
+    /// The time the AI has left before its next move, to budget its search. Ignored by default.
+    fn set_time_left(&mut self, _clock: &PlayerClock) {}
+
+    /// The probability that the side the last `best_move` played for wins, for AIs that estimate it.
+    fn win_rate_estimate(&self) -> Option<f32> {
+        None
+    }
+
+    /// The komi for AIs that score positions. Ignored by default.
+    fn set_komi(&mut self, _komi: f32) {}
+
+    /// The candidate moves of the last search, the most visited first. Empty for AIs that do not search a tree.
+    fn analysis(&self) -> Vec<MoveAnalysis> {
+        Vec::new()
+    }
+}
+
+/// Lets boxed AIs like those of `create_ai` be used where a `GoAI` type is expected.
+impl<A: GoAI + ?Sized> GoAI for Box<A> {
+    fn set_game(&mut self, game: GoGame) {
+        (**self).set_game(game);
+    }
+
+    fn best_move(&mut self) -> Option<GoCoordinates> {
+        (**self).best_move()
+    }
+
+    fn visit_counts(&self) -> Vec<(Option<GoCoordinates>, u32)> {
+        (**self).visit_counts()
+    }
+
+    fn start_pondering(&mut self) {
+        (**self).start_pondering();
+    }
+
+    fn stop_pondering(&mut self) {
+        (**self).stop_pondering();
+    }
+
+    fn is_pondering(&self) -> bool {
+        (**self).is_pondering()
+    }
+
+    fn set_time_left(&mut self, clock: &PlayerClock) {
+        (**self).set_time_left(clock);
+    }
+
+    fn win_rate_estimate(&self) -> Option<f32> {
+        (**self).win_rate_estimate()
+    }
+
+    fn set_komi(&mut self, komi: f32) {
+        (**self).set_komi(komi);
+    }
+
+    fn analysis(&self) -> Vec<MoveAnalysis> {
+        (**self).analysis()
+    }
+}
+
+pub const DEFAULT_RESIGN_THRESHOLD: f32 = 0.1;
+pub const DEFAULT_RESIGN_MOVES: usize = 3;
+
+/// Decides to resign once the win rate of an AI stayed below `threshold` for `moves` moves in a row.
+#[derive(Clone, Debug)]
+pub struct ResignPolicy {
+    threshold: f32,
+    moves: usize,
+    losing_moves: usize,
+}
+
+impl ResignPolicy {
+    pub fn new(threshold: f32, moves: usize) -> Self {
+        Self {threshold, moves: moves.max(1), losing_moves: 0}
+    }
+
+    /// Forgets the earlier win rates, for example after an undo.
+    pub fn reset(&mut self) {
+        self.losing_moves = 0;
+    }
+
+    /// Records the win rate of a move and returns whether to resign.
+    pub fn should_resign(&mut self, win_rate: f32) -> bool {
+        if win_rate < self.threshold {
+            self.losing_moves += 1;
+        } else {
+            self.losing_moves = 0;
+        }
+        self.losing_moves >= self.moves
+    }
+}
+
+/// Points where playing can not change the outcome for `color`: its own true eyes
+/// and the regions that Benson's algorithm already awarded to either color.
+pub fn settled_points(board: &GoBoard, color: GoStone) -> GoBoardMask {
+    let (_, mut result) = unconditional_life(board, color);
+    let (_, opponent_territory) = unconditional_life(board, color.opponent_color());
+
+    for i in 0..9 * 9 {
+        let coordinates = GoCoordinates::from(i);
+        if opponent_territory.get(coordinates) || is_true_eye(board, coordinates, color) {
+            result.set(coordinates, true);
+        }
+    }
+    result
+}
+
+/// Moves of `color` that start a working ladder against an opponent group, with the size of that group.
+pub fn ladder_captures(board: &GoBoard, color: GoStone) -> Vec<(GoCoordinates, usize)> {
+    let mut result: Vec<(GoCoordinates, usize)> = Vec::new();
+
+    for chain in chains(board, color.opponent_color()) {
+        if board.liberties(chain[0]).len() > 2 {
+            continue;
+        }
+
+        if let Some(sequence) = ladder_capture(board, chain[0], color) {
+            match result.iter_mut().find(|(coordinates, _)| *coordinates == sequence[0]) {
+                Some(entry) => entry.1 += chain.len(),
+                None => result.push((sequence[0], chain.len())),
+            }
+        }
+    }
+
+    result
+}
+
+/// Whether the group of `color` playing at `coordinates` could be captured in a ladder afterwards.
+pub fn runs_into_ladder(board: &GoBoard, coordinates: GoCoordinates, color: GoStone) -> bool {
+    match board.try_play(coordinates, color) {
+        Some((new_board, _)) => new_board.liberties(coordinates).len() <= 2 && is_ladder_captured(&new_board, coordinates, color.opponent_color()),
+        None => false,
+    }
+}
+
+/// Plays every legal move outside the settled points and ladders, and picks one of those where
+/// `evaluate` gives the best score for the player who moved, plus a bonus for starting a working ladder.
+pub fn choose_by_evaluation<R: Rng, F: Fn(&GoBoard, GoStone) -> i64>(game: &mut GoGame, random: &mut R, evaluate: F) -> Option<GoCoordinates> {
+    let mut best_moves = Vec::new();
+    let mut best_score = i64::MIN;
+    let color = game.current_turn();
+    let ladders = ladder_captures(game.current_board(), color);
+    let settled = settled_points(game.current_board(), color);
+
+    for x in 0..9 {
+        for y in 0..9 {
+            let coordinates = GoCoordinates::new(x, y);
+            if settled.get(coordinates) || runs_into_ladder(game.current_board(), coordinates, color) {
+                continue;
+            }
+            let ladder_bonus = ladders.iter().find(|(c, _)| *c == coordinates).map_or(0, |&(_, stones)| 2 * stones as i64);
+
+            if game.play_stone(coordinates).is_ok() {
+                let score = evaluate(game.current_board(), color) + ladder_bonus;
+
+                if score > best_score {
+                    best_score = score;
+                    best_moves.clear();
+                }
+                if score >= best_score {
+                    best_moves.push(coordinates);
+                }
+
+                game.undo().unwrap();
+            }
+        }
+    }
+
+    best_moves.choose(random).copied()
+}
+
+/// The AIs that play without any files.
+pub const AI_NAMES: &[&str] = &["random", "voronoi", "influence", "mcts"];
+/// The network AI, which plays only with trained weights from `WEIGHTS_FILE`.
+pub const PUCT_AI: &str = "puct";
+pub const WEIGHTS_FILE: &str = "weights.bin";
+
+/// Loads the network of `PUCT_AI` from `WEIGHTS_FILE`.
+pub fn load_weights() -> io::Result<Network> {
+    Network::load_file(WEIGHTS_FILE)
+}
+
+/// `AI_NAMES`, followed by `PUCT_AI` if its weights could be loaded. The weights are only
+/// loaded on the first call.
+pub fn available_ai_names() -> Vec<&'static str> {
+    static WEIGHTS_LOADED: OnceLock<bool> = OnceLock::new();
+    let mut result = AI_NAMES.to_vec();
+    if *WEIGHTS_LOADED.get_or_init(|| load_weights().is_ok()) {
+        result.push(PUCT_AI);
+    }
+    result
+}
+
+/// Creates the AI called `name` in `available_ai_names`, seeded with `seed`.
+/// Returns `None` for an unknown name, or for `PUCT_AI` without weights.
+pub fn create_ai(name: &str, seed: u64) -> Option<Box<dyn GoAI + Send>> {
+    match name {
+        "random" => Some(Box::new(RandomAI::with_seed(seed))),
+        "voronoi" => Some(Box::new(voronoi_ai::VoronoiAI::with_seed(seed))),
+        "influence" => Some(Box::new(influence_ai::InfluenceAI::with_seed(seed))),
+        "mcts" => Some(Box::new(mcts_ai::MctsAI::with_seed(seed, mcts_ai::DEFAULT_PLAYOUTS))),
+        PUCT_AI => Some(Box::new(puct_ai::PuctAI::new(load_weights().ok()?))),
+        _ => None,
+    }
 }
 
 pub struct RandomAI {
@@ -20,7 +267,11 @@ pub struct RandomAI {
 
 impl RandomAI {
     pub fn new() -> Self {
-        Self {game: GoGame::new(), random: SeedableRng::seed_from_u64(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs())}
+        Self::with_seed(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {game: GoGame::new(), random: SeedableRng::seed_from_u64(seed)}
     }
 }
 
@@ -30,7 +281,37 @@ impl GoAI for RandomAI {
     }
 
     fn best_move(&mut self) -> Option<GoCoordinates> {
+        let board = self.game.current_board();
+        let color = self.game.current_turn();
+        if let Some(&(coordinates, _)) = ladder_captures(board, color).first() {
+            return Some(coordinates);
+        }
+
         let dist = Uniform::from(0..9);
-        Some(GoCoordinates::new(dist.sample(&mut self.random), dist.sample(&mut self.random)))
+        let mut coordinates = GoCoordinates::new(dist.sample(&mut self.random), dist.sample(&mut self.random));
+        for _ in 0..9 * 9 {
+            if !runs_into_ladder(board, coordinates, color) {
+                break;
+            }
+            coordinates = GoCoordinates::new(dist.sample(&mut self.random), dist.sample(&mut self.random));
+        }
+        Some(coordinates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ResignPolicy;
+
+    #[test]
+    fn test_resign_policy() {
+        let mut policy = ResignPolicy::new(0.1, 2);
+        assert!(!policy.should_resign(0.05));
+        assert!(!policy.should_resign(0.5));
+        assert!(!policy.should_resign(0.05));
+        assert!(policy.should_resign(0.02));
+
+        policy.reset();
+        assert!(!policy.should_resign(0.02));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,314 @@
+use crate::go::board::{GoCoordinates, GoStone};
+use crate::go::clock::PlayerClock;
+use crate::go::GoGame;
+use crate::go::ai::{GoAI, MoveAnalysis};
+use crate::go::nn::Evaluator;
+use crate::go::playout::PlayoutState;
+use crate::go::scoring::DEFAULT_KOMI;
+use rand_pcg::Pcg64Mcg;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_VISITS: usize = 200;
+pub const EXPLORATION: f32 = 1.5;
+pub const DIRICHLET_ALPHA: f32 = 0.3;
+/// The share of the root priors replaced by Dirichlet noise while exploring.
+pub const NOISE_FRACTION: f32 = 0.25;
+/// The number of moves at the start of a game that are sampled by visit count while exploring.
+pub const DEFAULT_SAMPLING_MOVES: usize = 16;
+
+/// Tree search guided by an evaluator: its priors steer the PUCT selection and its
+/// value replaces the random playouts of `MctsAI`.
+pub struct PuctAI<E: Evaluator> {
+    game: GoGame,
+    evaluator: E,
+    visits: usize,
+    time_budget: Option<Duration>,
+    komi: f32,
+    root: Node,
+    exploration: Option<Exploration>,
+}
+
+/// Randomness for self-play, so that games from the same network differ.
+struct Exploration {
+    random: Pcg64Mcg,
+    sampling_moves: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    game_move: Option<GoCoordinates>,
+    prior: f32,
+    visits: u32,
+    /// Summed win probability of the player who made `game_move`.
+    value_sum: f32,
+    expanded: bool,
+    children: Vec<Node>,
+}
+
+impl<E: Evaluator> PuctAI<E> {
+    pub fn new(evaluator: E) -> Self {
+        Self::with_visits(evaluator, DEFAULT_VISITS)
+    }
+
+    pub fn with_visits(evaluator: E, visits: usize) -> Self {
+        Self {game: GoGame::new(), evaluator, visits, time_budget: None, komi: DEFAULT_KOMI, root: Node::new(None, 1.0), exploration: None}
+    }
+
+    /// Mixes Dirichlet noise seeded with `seed` into the root priors of every search, and picks the
+    /// first `sampling_moves` moves of a game with probability proportional to their visits.
+    pub fn set_exploration(&mut self, seed: u64, sampling_moves: usize) {
+        self.exploration = Some(Exploration {random: SeedableRng::seed_from_u64(seed), sampling_moves});
+    }
+
+    pub fn set_komi(&mut self, komi: f32) {
+        self.komi = komi;
+    }
+
+    pub fn evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// The estimated probability that the side to move wins, from the last search.
+    pub fn win_rate(&self) -> f32 {
+        if self.root.visits == 0 {
+            0.5
+        } else {
+            1.0 - self.root.value_sum / self.root.visits as f32
+        }
+    }
+
+    /// Expands or scores the leaf reached from `node` and returns the win probability
+    /// of the player to move at `node`.
+    fn iterate(&self, node: &mut Node, game: &mut GoGame) -> f32 {
+        let value = if game.is_over() || game.consecutive_passes() >= 2 {
+            let (black, white) = PlayoutState::from_game(game).area_score();
+            let margin = black as f32 - white as f32 - self.komi;
+            let black_wins = if margin > 0.0 {1.0} else if margin < 0.0 {0.0} else {0.5};
+            if game.current_turn() == GoStone::BLACK {black_wins} else {1.0 - black_wins}
+        } else if !node.expanded {
+            let evaluation = self.evaluator.evaluate(game);
+            node.children = evaluation.priors.iter().map(|&(game_move, prior)| Node::new(game_move, prior)).collect();
+            node.expanded = true;
+            evaluation.value
+        } else {
+            let child = node.select_child();
+            game.play(child.game_move).unwrap();
+            1.0 - self.iterate(child, game)
+        };
+
+        node.visits += 1;
+        node.value_sum += 1.0 - value;
+        value
+    }
+}
+
+impl Node {
+    fn new(game_move: Option<GoCoordinates>, prior: f32) -> Self {
+        Self {game_move, prior, visits: 0, value_sum: 0.0, expanded: false, children: Vec::new()}
+    }
+
+    fn select_child(&mut self) -> &mut Node {
+        let scale = EXPLORATION * (self.visits as f32).sqrt();
+        let mut best_index = 0;
+        let mut best_value = f32::NEG_INFINITY;
+
+        for (index, child) in self.children.iter().enumerate() {
+            let mean = if child.visits == 0 {0.5} else {child.value_sum / child.visits as f32};
+            let value = mean + scale * child.prior / (1.0 + child.visits as f32);
+            if value > best_value {
+                best_value = value;
+                best_index = index;
+            }
+        }
+
+        &mut self.children[best_index]
+    }
+
+    /// The most visited line of play from this node.
+    fn variation(&self) -> Vec<Option<GoCoordinates>> {
+        let mut result = vec![self.game_move];
+        let mut node = self;
+        while let Some(child) = node.children.iter().filter(|child| child.visits > 0).max_by_key(|child| child.visits) {
+            result.push(child.game_move);
+            node = child;
+        }
+        result
+    }
+}
+
+impl<E: Evaluator> GoAI for PuctAI<E> {
+    fn set_game(&mut self, game: GoGame) {
+        self.root = Node::new(None, 1.0);
+        self.game = game;
+    }
+
+    /// Runs `visits` visits, or with a clock keeps searching until the time budget of the move is used up.
+    fn best_move(&mut self) -> Option<GoCoordinates> {
+        let deadline = self.time_budget.map(|budget| Instant::now() + budget);
+        if self.game.is_over() {
+            self.root = Node::new(None, 1.0);
+            return None;
+        }
+
+        let mut root = Node::new(None, 1.0);
+        self.iterate(&mut root, &mut self.game.clone());
+        if let Some(exploration) = &mut self.exploration {
+            let noise = dirichlet_noise(&mut exploration.random, root.children.len());
+            for (child, noise) in root.children.iter_mut().zip(noise) {
+                child.prior = (1.0 - NOISE_FRACTION) * child.prior + NOISE_FRACTION * noise;
+            }
+        }
+        let visits = if deadline.is_some() {usize::MAX} else {self.visits.max(2)};
+        for _ in 1..visits {
+            let mut game = self.game.clone();
+            self.iterate(&mut root, &mut game);
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break;
+            }
+        }
+
+        self.root = root;
+        match &mut self.exploration {
+            Some(exploration) if self.game.moves().len() < exploration.sampling_moves => {
+                let total: u32 = self.root.children.iter().map(|child| child.visits).sum();
+                let mut pick = exploration.random.gen_range(0..total.max(1));
+                self.root.children.iter().find(|child| {
+                    let found = pick < child.visits;
+                    pick = pick.saturating_sub(child.visits);
+                    found
+                }).and_then(|child| child.game_move)
+            }
+            _ => self.root.children.iter().max_by_key(|child| child.visits).and_then(|child| child.game_move),
+        }
+    }
+
+    fn visit_counts(&self) -> Vec<(Option<GoCoordinates>, u32)> {
+        self.root.children.iter().map(|child| (child.game_move, child.visits)).collect()
+    }
+
+    fn set_time_left(&mut self, clock: &PlayerClock) {
+        self.time_budget = clock.move_budget();
+    }
+
+    fn win_rate_estimate(&self) -> Option<f32> {
+        Some(self.win_rate())
+    }
+
+    fn set_komi(&mut self, komi: f32) {
+        PuctAI::set_komi(self, komi);
+    }
+
+    /// Without a score estimate from the evaluator, `score_lead` is `None`.
+    fn analysis(&self) -> Vec<MoveAnalysis> {
+        let mut children: Vec<_> = self.root.children.iter().rev().filter(|child| child.visits > 0).collect();
+        children.sort_by_key(|child| Reverse(child.visits));
+        children.into_iter().map(|child| MoveAnalysis {
+            game_move: child.game_move,
+            visits: child.visits,
+            win_rate: child.value_sum / child.visits as f32,
+            score_lead: None,
+            variation: child.variation(),
+        }).collect()
+    }
+}
+
+/// `count` weights drawn from a symmetric Dirichlet distribution with `DIRICHLET_ALPHA`.
+fn dirichlet_noise(random: &mut Pcg64Mcg, count: usize) -> Vec<f32> {
+    let samples: Vec<f32> = (0..count).map(|_| gamma(random, DIRICHLET_ALPHA)).collect();
+    let sum: f32 = samples.iter().sum();
+    samples.into_iter().map(|sample| if sum > 0.0 {sample / sum} else {1.0 / count as f32}).collect()
+}
+
+/// A sample of the gamma distribution with shape `alpha` and scale 1, after Marsaglia and Tsang.
+fn gamma(random: &mut Pcg64Mcg, alpha: f32) -> f32 {
+    if alpha < 1.0 {
+        return gamma(random, alpha + 1.0) * (1.0 - random.gen::<f32>()).powf(1.0 / alpha);
+    }
+
+    let d = alpha - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        let normal = (-2.0 * (1.0 - random.gen::<f32>()).ln()).sqrt() * (2.0 * PI * random.gen::<f32>()).cos();
+        let v = (1.0 + c * normal).powi(3);
+        if v > 0.0 && (1.0 - random.gen::<f32>()).ln() < 0.5 * normal * normal + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PuctAI;
+    use crate::go::ai::GoAI;
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+    use crate::go::clock::{PlayerClock, TimeControl};
+    use crate::go::nn::{Evaluation, Evaluator};
+    use crate::go::GoGame;
+    use std::time::Duration;
+
+    /// Prefers one point and thinks every position is even.
+    struct FixedEvaluator(GoCoordinates);
+
+    impl Evaluator for FixedEvaluator {
+        fn evaluate(&self, game: &GoGame) -> Evaluation {
+            let mut priors: Vec<_> = (0..9 * 9).map(GoCoordinates::from)
+                .filter(|&c| game.is_legal(c))
+                .map(|c| (Some(c), if c == self.0 {0.9} else {0.001}))
+                .collect();
+            priors.push((None, 0.001));
+            Evaluation {priors, value: 0.5}
+        }
+    }
+
+    #[test]
+    fn test_prior_guides_search() {
+        let mut ai = PuctAI::with_visits(FixedEvaluator(GoCoordinates::new(2, 6)), 50);
+        ai.set_game(GoGame::from_board(GoBoard::new(), GoStone::BLACK));
+
+        assert_eq!(ai.best_move(), Some(GoCoordinates::new(2, 6)));
+        assert_eq!(ai.visit_counts().iter().map(|&(_, visits)| visits).sum::<u32>(), 49);
+        assert_eq!(ai.analysis()[0].game_move, Some(GoCoordinates::new(2, 6)));
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let mut ai = PuctAI::with_visits(FixedEvaluator(GoCoordinates::new(2, 6)), 5);
+        ai.set_game(GoGame::new());
+        ai.set_time_left(&PlayerClock::new(TimeControl::Absolute {main_time: Duration::from_secs(2)}));
+
+        assert_eq!(ai.best_move(), Some(GoCoordinates::new(2, 6)));
+        assert!(ai.visit_counts().iter().map(|&(_, visits)| visits).sum::<u32>() > 5);
+    }
+
+    #[test]
+    fn test_finished_game() {
+        let mut ai = PuctAI::with_visits(FixedEvaluator(GoCoordinates::new(2, 6)), 20);
+        let mut game = GoGame::new();
+        ai.set_game(game.clone());
+        ai.best_move();
+        game.resign(GoStone::BLACK);
+        ai.set_game(game);
+        assert_eq!(ai.best_move(), None);
+        assert!(ai.analysis().is_empty());
+    }
+
+    #[test]
+    fn test_exploration_varies_games() {
+        let play = |seed| {
+            let mut ai = PuctAI::with_visits(FixedEvaluator(GoCoordinates::new(2, 6)), 30);
+            ai.set_exploration(seed, 4);
+            let mut game = GoGame::new();
+            for _ in 0..6 {
+                ai.set_game(game.clone());
+                game.play(ai.best_move()).unwrap();
+            }
+            game.moves().to_vec()
+        };
+
+        assert_eq!(play(1), play(1));
+        assert_ne!(play(1), play(2));
+    }
+}
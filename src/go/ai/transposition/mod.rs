@@ -0,0 +1,202 @@
+use crate::go::board::GoCoordinates;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+pub const DEFAULT_SHARDS: usize = 16;
+pub const BUCKET_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The value is at least this, after a beta cutoff.
+    Lower,
+    /// The value is at most this, when no move raised alpha.
+    Upper,
+}
+
+/// What a search knows about a position. Alpha-beta searches use the depth, value and bound,
+/// Monte Carlo searches the visits and wins. Both may record the best move, where `None` is a pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TableEntry {
+    pub depth: u16,
+    pub value: f32,
+    pub bound: Bound,
+    pub best_move: Option<Option<GoCoordinates>>,
+    pub visits: u32,
+    pub wins: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    hash: u64,
+    generation: u8,
+    entry: TableEntry,
+}
+
+/// A fixed-size table from position hashes, usually `zobrist::hash`, to `TableEntry`s that
+/// can be shared between threads. The table is split into shards behind their own locks, and
+/// every hash maps to a bucket of `BUCKET_SIZE` slots in one shard. When a bucket is full, the
+/// entry of an older search or else the one with the least work behind it is replaced.
+pub struct TranspositionTable {
+    shards: Vec<Mutex<Vec<Option<Slot>>>>,
+    buckets_per_shard: usize,
+    generation: AtomicU8,
+}
+
+impl TableEntry {
+    pub fn new() -> Self {
+        Self {depth: 0, value: 0.0, bound: Bound::Exact, best_move: None, visits: 0, wins: 0.0}
+    }
+
+    /// An alpha-beta result.
+    pub fn searched(depth: u16, value: f32, bound: Bound, best_move: Option<Option<GoCoordinates>>) -> Self {
+        Self {depth, value, bound, best_move, ..Self::new()}
+    }
+
+    /// How much work went into the entry, for the replacement policy.
+    fn work(&self) -> u32 {
+        self.depth as u32 + (32 - self.visits.leading_zeros())
+    }
+}
+
+impl Default for TableEntry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranspositionTable {
+    /// A table with room for about `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_shards(capacity, DEFAULT_SHARDS)
+    }
+
+    pub fn with_shards(capacity: usize, shards: usize) -> Self {
+        let shards = shards.max(1);
+        let buckets_per_shard = (capacity / (shards * BUCKET_SIZE)).max(1);
+        Self {
+            shards: (0..shards).map(|_| Mutex::new(vec![None; buckets_per_shard * BUCKET_SIZE])).collect(),
+            buckets_per_shard,
+            generation: AtomicU8::new(0),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shards.len() * self.buckets_per_shard * BUCKET_SIZE
+    }
+
+    /// The number of occupied slots.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().unwrap().iter().filter(|slot| slot.is_some()).count()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Marks the entries stored so far as old, so they are replaced first. They can still be found.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            for slot in shard.lock().unwrap().iter_mut() {
+                *slot = None;
+            }
+        }
+    }
+
+    fn location(&self, hash: u64) -> (usize, usize) {
+        let shard = (hash % self.shards.len() as u64) as usize;
+        let bucket = ((hash / self.shards.len() as u64) % self.buckets_per_shard as u64) as usize;
+        (shard, bucket * BUCKET_SIZE)
+    }
+
+    pub fn get(&self, hash: u64) -> Option<TableEntry> {
+        let (shard, start) = self.location(hash);
+        let slots = self.shards[shard].lock().unwrap();
+        slots[start..start + BUCKET_SIZE].iter().flatten().find(|slot| slot.hash == hash).map(|slot| slot.entry)
+    }
+
+    pub fn store(&self, hash: u64, entry: TableEntry) {
+        self.update(hash, |stored| *stored = entry);
+    }
+
+    /// Changes the entry of `hash` in place, starting from `TableEntry::new()` if there is none.
+    pub fn update<F: FnOnce(&mut TableEntry)>(&self, hash: u64, update: F) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let (shard, start) = self.location(hash);
+        let mut slots = self.shards[shard].lock().unwrap();
+        let bucket = &mut slots[start..start + BUCKET_SIZE];
+
+        let index = bucket.iter().position(|slot| slot.is_some_and(|slot| slot.hash == hash))
+            .or_else(|| bucket.iter().position(|slot| slot.is_none()))
+            .unwrap_or_else(|| {
+                (0..BUCKET_SIZE)
+                    .min_by_key(|&i| {
+                        let slot = bucket[i].unwrap();
+                        (slot.generation == generation, slot.entry.work())
+                    })
+                    .unwrap()
+            });
+
+        let mut entry = match bucket[index] {
+            Some(slot) if slot.hash == hash => slot.entry,
+            _ => TableEntry::new(),
+        };
+        update(&mut entry);
+        bucket[index] = Some(Slot {hash, generation, entry});
+    }
+
+    /// Adds Monte Carlo statistics to the entry of `hash`.
+    pub fn add_visits(&self, hash: u64, visits: u32, wins: f32) {
+        self.update(hash, |entry| {
+            entry.visits += visits;
+            entry.wins += wins;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bound, TableEntry, TranspositionTable, BUCKET_SIZE};
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_replacement_keeps_deep_and_recent_entries() {
+        let table = TranspositionTable::with_shards(BUCKET_SIZE, 1);
+        for hash in 0..BUCKET_SIZE as u64 {
+            table.store(hash, TableEntry::searched(hash as u16 + 1, 0.0, Bound::Exact, None));
+        }
+        table.store(100, TableEntry::searched(10, 1.0, Bound::Lower, None));
+        assert_eq!(table.get(0), None);
+        assert_eq!(table.get(100).unwrap().bound, Bound::Lower);
+
+        table.new_search();
+        table.store(200, TableEntry::searched(1, 0.0, Bound::Exact, None));
+        assert_eq!(table.get(1), None);
+        assert!(table.get(100).is_some());
+        assert_eq!(table.len(), BUCKET_SIZE);
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let table = Arc::new(TranspositionTable::new(1 << 12));
+        let handles: Vec<_> = (0..4).map(|_| {
+            let table = table.clone();
+            thread::spawn(move || {
+                for hash in 0..100 {
+                    table.add_visits(hash, 1, 0.5);
+                }
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(table.get(42).unwrap().visits, 4);
+        assert_eq!(table.get(42).unwrap().wins, 2.0);
+    }
+}
@@ -1,9 +1,9 @@
 use crate::go::board::GoCoordinates;
+use crate::go::board::GoStone;
 use crate::go::GoGame;
-use crate::go::ai::GoAI;
+use crate::go::ai::{GoAI, choose_by_evaluation};
 use rand_pcg::Pcg64Mcg;
 use rand::SeedableRng;
-use rand::seq::SliceRandom;
 use std::time::SystemTime;
 
 pub struct VoronoiAI {
@@ -13,7 +13,11 @@ pub struct VoronoiAI {
 
 impl VoronoiAI {
     pub fn new() -> Self {
-        Self {game: GoGame::new(), random: SeedableRng::seed_from_u64(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs())}
+        Self::with_seed(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {game: GoGame::new(), random: SeedableRng::seed_from_u64(seed)}
     }
 }
 
@@ -23,32 +27,10 @@ impl GoAI for VoronoiAI {
     }
 
     fn best_move(&mut self) -> Option<GoCoordinates> {
-        let mut best_moves = Vec::new();
-        let mut best_diff = i64::min_value();
-
-        for x in 0..9 {
-            for y in 0..9 {
-                let coordinates = GoCoordinates::new(x, y);
-
-                if self.game.play_stone(coordinates).is_ok() {
-                    let (black_voronoi, white_voronoi) = self.game.current_board().voronoi_score();
-                    let black_voronoi = black_voronoi as i64;
-                    let white_voronoi = white_voronoi as i64;
-                    let diff = white_voronoi - black_voronoi;
-
-                    if diff > best_diff {
-                        best_diff = diff;
-                        best_moves.clear();
-                    }
-                    if diff >= best_diff {
-                        best_moves.push(coordinates);
-                    }
-
-                    self.game.undo().unwrap();
-                }
-            }
-        }
-
-        best_moves.choose(&mut self.random).copied()
+        choose_by_evaluation(&mut self.game, &mut self.random, |board, color| {
+            let (black_voronoi, white_voronoi) = board.voronoi_score();
+            let diff = white_voronoi as i64 - black_voronoi as i64;
+            if color == GoStone::BLACK {-diff} else {diff}
+        })
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,131 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+use crate::go::board::analysis::{chains, enclosed_regions};
+
+/// Benson's algorithm. Returns the stones of `color` that can not be captured
+/// even if `color` always passes, together with the regions they secure.
+pub fn unconditional_life(board: &GoBoard, color: GoStone) -> (GoBoardMask, GoBoardMask) {
+    let chains = chains(board, color);
+    let regions = enclosed_regions(board, color);
+
+    let mut chain_ids = vec![usize::MAX; 9 * 9];
+    for (id, chain) in chains.iter().enumerate() {
+        for &stone in chain {
+            chain_ids[usize::from(stone)] = id;
+        }
+    }
+
+    let mut bordering_chains = Vec::new();
+    let mut vital_to = Vec::new();
+    for region in &regions {
+        let mut bordering = Vec::new();
+        for point in region {
+            for neighbor in point.neighbors() {
+                let id = chain_ids[usize::from(neighbor)];
+                if id != usize::MAX && !bordering.contains(&id) {
+                    bordering.push(id);
+                }
+            }
+        }
+
+        let vital: Vec<usize> = bordering.iter().copied().filter(|&id| {
+            region.iter()
+                .filter(|&&point| board.get_stone(point).is_none())
+                .all(|point| point.neighbors().iter().any(|&n| chain_ids[usize::from(n)] == id))
+        }).collect();
+
+        bordering_chains.push(bordering);
+        vital_to.push(vital);
+    }
+
+    let mut chain_alive = vec![true; chains.len()];
+    let mut region_healthy = vec![true; regions.len()];
+
+    loop {
+        let mut changed = false;
+
+        for (chain, alive) in chain_alive.iter_mut().enumerate() {
+            if !*alive {
+                continue;
+            }
+            let vital_regions = (0..regions.len()).filter(|&r| region_healthy[r] && vital_to[r].contains(&chain)).count();
+            if vital_regions < 2 {
+                *alive = false;
+                changed = true;
+            }
+        }
+
+        for region in 0..regions.len() {
+            if region_healthy[region] && bordering_chains[region].iter().any(|&c| !chain_alive[c]) {
+                region_healthy[region] = false;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut alive = GoBoardMask::new();
+    for (id, chain) in chains.iter().enumerate() {
+        if chain_alive[id] {
+            for &stone in chain {
+                alive.set(stone, true);
+            }
+        }
+    }
+
+    let mut territory = GoBoardMask::new();
+    for (id, region) in regions.iter().enumerate() {
+        if region_healthy[id] && !vital_to[id].is_empty() {
+            for &point in region {
+                territory.set(point, true);
+            }
+        }
+    }
+
+    (alive, territory)
+}
+
+pub fn unconditionally_alive_stones(board: &GoBoard, color: GoStone) -> GoBoardMask {
+    unconditional_life(board, color).0
+}
+
+pub fn is_unconditionally_alive(board: &GoBoard, coordinates: GoCoordinates) -> bool {
+    let color = board.get_stone(coordinates);
+    !color.is_none() && unconditionally_alive_stones(board, color).get(coordinates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_unconditionally_alive, unconditional_life};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_two_eyes_are_alive() {
+        let board = GoBoard::from_rows(&[
+            ".X.XO....",
+            "XXXXO....",
+            "OOOOO....",
+        ]);
+
+        assert!(is_unconditionally_alive(&board, GoCoordinates::new(0, 1)));
+        assert!(!is_unconditionally_alive(&board, GoCoordinates::new(4, 0)));
+
+        let (_, territory) = unconditional_life(&board, GoStone::BLACK);
+        assert!(territory.get(GoCoordinates::new(0, 0)));
+        assert!(territory.get(GoCoordinates::new(2, 0)));
+        assert!(!territory.get(GoCoordinates::new(5, 5)));
+    }
+
+    #[test]
+    fn test_one_eye_is_not_alive() {
+        let board = GoBoard::from_rows(&[
+            "..XO.....",
+            "XXXO.....",
+            "OOOO.....",
+        ]);
+
+        assert!(!is_unconditionally_alive(&board, GoCoordinates::new(0, 1)));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::board::analysis::enclosed_regions;
+
+/// Enclosed regions larger than this are not considered eye spaces.
+pub const MAX_EYE_SPACE_SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EyeType {
+    NoEye,
+    FalseEye,
+    TrueEye,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EyeSpace {
+    points: Vec<GoCoordinates>,
+    opponent_stones: usize,
+}
+
+/// Classifies the single point `coordinates` as an eye of `color`.
+/// An empty point surrounded by `color` is a false eye if the opponent holds
+/// two of its diagonals, or one if the point is on the edge.
+pub fn eye_type(board: &GoBoard, coordinates: GoCoordinates, color: GoStone) -> EyeType {
+    assert!(!color.is_none());
+    if !board.get_stone(coordinates).is_none() || coordinates.neighbors().iter().any(|&n| board.get_stone(n) != color) {
+        return EyeType::NoEye;
+    }
+
+    let diagonals = coordinates.diagonals();
+    let opponent = diagonals.iter().filter(|&&d| board.get_stone(d) == color.opponent_color()).count();
+    let limit = if diagonals.len() < 4 {1} else {2};
+
+    if opponent >= limit {
+        EyeType::FalseEye
+    } else {
+        EyeType::TrueEye
+    }
+}
+
+pub fn is_true_eye(board: &GoBoard, coordinates: GoCoordinates, color: GoStone) -> bool {
+    eye_type(board, coordinates, color) == EyeType::TrueEye
+}
+
+/// The small regions enclosed by the color of the group at `coordinates` that touch the group.
+pub fn eye_spaces(board: &GoBoard, coordinates: GoCoordinates) -> Vec<EyeSpace> {
+    let color = board.get_stone(coordinates);
+    assert!(!color.is_none());
+    let group = board.group(coordinates);
+
+    enclosed_regions(board, color).into_iter()
+        .filter(|region| region.len() <= MAX_EYE_SPACE_SIZE)
+        .filter(|region| region.iter().any(|point| point.neighbors().iter().any(|n| group.contains(n))))
+        .map(|points| {
+            let opponent_stones = points.iter().filter(|&&p| !board.get_stone(p).is_none()).count();
+            EyeSpace {points, opponent_stones}
+        })
+        .collect()
+}
+
+impl EyeSpace {
+    pub fn points(&self) -> &[GoCoordinates] {
+        &self.points
+    }
+
+    pub fn size(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn opponent_stones(&self) -> usize {
+        self.opponent_stones
+    }
+
+    pub fn contains(&self, coordinates: GoCoordinates) -> bool {
+        self.points.contains(&coordinates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{eye_spaces, eye_type, EyeType};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_eye_types() {
+        let board = GoBoard::from_rows(&[
+            ".X.X.....",
+            "XXXX.....",
+            ".........",
+            "...X.....",
+            "..X.X....",
+            "..OXO....",
+        ]);
+
+        assert_eq!(eye_type(&board, GoCoordinates::new(0, 0), GoStone::BLACK), EyeType::TrueEye);
+        assert_eq!(eye_type(&board, GoCoordinates::new(2, 0), GoStone::BLACK), EyeType::TrueEye);
+        assert_eq!(eye_type(&board, GoCoordinates::new(3, 4), GoStone::BLACK), EyeType::FalseEye);
+        assert_eq!(eye_type(&board, GoCoordinates::new(0, 0), GoStone::WHITE), EyeType::NoEye);
+        assert_eq!(eye_type(&board, GoCoordinates::new(4, 0), GoStone::BLACK), EyeType::NoEye);
+    }
+
+    #[test]
+    fn test_eye_spaces() {
+        let board = GoBoard::from_rows(&[
+            "..X.X....",
+            "XXXXX....",
+        ]);
+
+        let spaces = eye_spaces(&board, GoCoordinates::new(0, 1));
+        assert_eq!(spaces.len(), 2);
+        assert_eq!(spaces.iter().map(|s| s.size()).sum::<usize>(), 3);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+/// Ladders on a 9x9 board are never longer than this.
+pub const MAX_LADDER_DEPTH: usize = 2 * 9 * 9;
+
+/// Reads whether the group at `coordinates`, having one or two liberties, can be
+/// captured by a ladder when `to_move` plays next. Returns the capturing sequence
+/// of alternating attacker and defender moves, ending with the capture.
+pub fn ladder_capture(board: &GoBoard, coordinates: GoCoordinates, to_move: GoStone) -> Option<Vec<GoCoordinates>> {
+    let defender = board.get_stone(coordinates);
+    assert!(!defender.is_none());
+    assert!(!to_move.is_none());
+
+    let mut sequence = Vec::new();
+    let captured = if to_move == defender {
+        if board.liberties(coordinates).len() != 1 {
+            return None;
+        }
+        !defend(board, coordinates, 0, &mut sequence)
+    } else {
+        attack(board, coordinates, 0, &mut sequence)
+    };
+
+    if captured {
+        Some(sequence)
+    } else {
+        None
+    }
+}
+
+pub fn is_ladder_captured(board: &GoBoard, coordinates: GoCoordinates, to_move: GoStone) -> bool {
+    ladder_capture(board, coordinates, to_move).is_some()
+}
+
+/// The attacker to move. Returns true if the group is captured, with the line in `sequence`.
+fn attack(board: &GoBoard, target: GoCoordinates, depth: usize, sequence: &mut Vec<GoCoordinates>) -> bool {
+    let defender = board.get_stone(target);
+    let attacker = defender.opponent_color();
+    let liberties = board.liberties(target);
+
+    if liberties.len() == 1 {
+        if board.try_play(liberties[0], attacker).is_some() {
+            sequence.push(liberties[0]);
+            return true;
+        }
+        return false;
+    }
+    if liberties.len() > 2 || depth >= MAX_LADDER_DEPTH {
+        return false;
+    }
+
+    for &liberty in &liberties {
+        if let Some((new_board, _)) = board.try_play(liberty, attacker) {
+            if new_board.get_stone(target) != defender {
+                sequence.push(liberty);
+                return true;
+            }
+
+            let length = sequence.len();
+            sequence.push(liberty);
+            if !defend(&new_board, target, depth + 1, sequence) {
+                return true;
+            }
+            sequence.truncate(length);
+        }
+    }
+
+    false
+}
+
+/// The defender to move with the group in atari. Returns true if it escapes; otherwise
+/// `sequence` holds the first refuted defense followed by the attacker's continuation.
+fn defend(board: &GoBoard, target: GoCoordinates, depth: usize, sequence: &mut Vec<GoCoordinates>) -> bool {
+    let defender = board.get_stone(target);
+    let mut refutation = None;
+
+    for escape in escapes(board, target) {
+        if let Some((new_board, _)) = board.try_play(escape, defender) {
+            let liberties = new_board.liberties(target).len();
+            if liberties >= 3 {
+                return true;
+            }
+
+            let mut line = vec![escape];
+            if liberties == 2 && !attack(&new_board, target, depth + 1, &mut line) {
+                return true;
+            }
+            if liberties == 1 {
+                line.push(new_board.liberties(target)[0]);
+            }
+            if refutation.is_none() {
+                refutation = Some(line);
+            }
+        }
+    }
+
+    if let Some(line) = refutation {
+        sequence.extend(line);
+    } else {
+        sequence.push(board.liberties(target)[0]);
+    }
+    false
+}
+
+/// Extending on the last liberty, or capturing an adjacent attacking chain that is in atari.
+fn escapes(board: &GoBoard, target: GoCoordinates) -> Vec<GoCoordinates> {
+    let attacker = board.get_stone(target).opponent_color();
+    let mut result = Vec::new();
+
+    for stone in board.group(target) {
+        for neighbor in stone.neighbors() {
+            if board.get_stone(neighbor) == attacker {
+                let liberties = board.liberties(neighbor);
+                if liberties.len() == 1 && !result.contains(&liberties[0]) {
+                    result.push(liberties[0]);
+                }
+            }
+        }
+    }
+
+    for liberty in board.liberties(target) {
+        if !result.contains(&liberty) {
+            result.push(liberty);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_ladder_captured, ladder_capture};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_ladder_works() {
+        let board = GoBoard::from_rows(&[
+            ".........",
+            ".........",
+            "...X.....",
+            "..XO.....",
+            "....X....",
+        ]);
+
+        let sequence = ladder_capture(&board, GoCoordinates::new(3, 3), GoStone::BLACK).unwrap();
+        assert_eq!(sequence.len() % 2, 1);
+
+        let mut board = board;
+        let mut color = GoStone::BLACK;
+        for coordinates in sequence {
+            board = board.try_play(coordinates, color).unwrap().0;
+            color = color.opponent_color();
+        }
+        assert!(board.get_stone(GoCoordinates::new(3, 3)).is_none());
+    }
+
+    #[test]
+    fn test_ladder_breaker() {
+        let board = GoBoard::from_rows(&[
+            ".........",
+            ".....O...",
+            "...X.....",
+            "..XO.....",
+            "....X....",
+            ".O.......",
+        ]);
+
+        assert!(!is_ladder_captured(&board, GoCoordinates::new(3, 3), GoStone::BLACK));
+    }
+
+    #[test]
+    fn test_defender_to_move() {
+        let board = GoBoard::from_rows(&[
+            ".........",
+            ".........",
+            "...X.....",
+            "..XOX....",
+            "....X....",
+        ]);
+
+        assert!(is_ladder_captured(&board, GoCoordinates::new(3, 3), GoStone::WHITE));
+
+        let board = GoBoard::from_rows(&[
+            ".........",
+            ".........",
+            "...X.....",
+            "..XO.....",
+            "....X....",
+        ]);
+
+        assert!(!is_ladder_captured(&board, GoCoordinates::new(3, 3), GoStone::WHITE));
+    }
+}
@@ -0,0 +1,50 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+
+pub mod eyes;
+pub mod benson;
+pub mod ladder;
+pub mod seki;
+
+/// All chains of `color` on the board.
+pub fn chains(board: &GoBoard, color: GoStone) -> Vec<Vec<GoCoordinates>> {
+    assert!(!color.is_none());
+    components(board, |stone| stone == color)
+}
+
+/// The maximal connected sets of points not occupied by `color`,
+/// which are the regions `color` encloses in the sense of Benson.
+pub fn enclosed_regions(board: &GoBoard, color: GoStone) -> Vec<Vec<GoCoordinates>> {
+    assert!(!color.is_none());
+    components(board, |stone| stone != color)
+}
+
+fn components<F: Fn(GoStone) -> bool>(board: &GoBoard, member: F) -> Vec<Vec<GoCoordinates>> {
+    let mut visited = GoBoardMask::new();
+    let mut result = Vec::new();
+
+    for i in 0..9 * 9 {
+        let start = GoCoordinates::from(i);
+        if visited.get(start) || !member(board.get_stone(start)) {
+            continue;
+        }
+
+        visited.set(start, true);
+        let mut stack = vec![start];
+        let mut component = Vec::new();
+
+        while let Some(coordinates) = stack.pop() {
+            component.push(coordinates);
+
+            for neighbor in coordinates.neighbors() {
+                if !visited.get(neighbor) && member(board.get_stone(neighbor)) {
+                    visited.set(neighbor, true);
+                    stack.push(neighbor);
+                }
+            }
+        }
+
+        result.push(component);
+    }
+
+    result
+}
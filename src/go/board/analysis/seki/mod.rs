@@ -0,0 +1,154 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+use crate::go::board::analysis::benson::unconditionally_alive_stones;
+use crate::go::board::analysis::chains;
+use crate::go::board::analysis::eyes::is_true_eye;
+
+#[derive(Clone, Debug)]
+pub struct SekiAnalysis {
+    stones: GoBoardMask,
+    shared_liberties: GoBoardMask,
+    eyes: GoBoardMask,
+}
+
+#[derive(Clone, Debug)]
+struct Candidate {
+    color: GoStone,
+    stones: Vec<GoCoordinates>,
+    liberties: Vec<GoCoordinates>,
+    alive: bool,
+}
+
+/// Finds chains that share liberties with opposing chains which neither side can
+/// approach without putting itself into atari. A chain in seki may have at most one
+/// eye, and every other liberty must be such a shared liberty.
+/// This is a local test: it does not read out what happens after a capture.
+pub fn find_seki(board: &GoBoard) -> SekiAnalysis {
+    let mut candidates = Vec::new();
+    for &color in &[GoStone::BLACK, GoStone::WHITE] {
+        let alive = unconditionally_alive_stones(board, color);
+        for chain in chains(board, color) {
+            let liberties = board.liberties(chain[0]);
+            if !alive.get(chain[0]) && liberties.len() >= 2 {
+                candidates.push(Candidate {color, stones: chain, liberties, alive: true});
+            }
+        }
+    }
+
+    loop {
+        let mut changed = false;
+
+        for i in 0..candidates.len() {
+            if !candidates[i].alive {
+                continue;
+            }
+
+            let candidate = &candidates[i];
+            let mut eyes = 0;
+            let mut shared = 0;
+            let mut compatible = true;
+            for &liberty in &candidate.liberties {
+                if is_true_eye(board, liberty, candidate.color) {
+                    eyes += 1;
+                } else if candidates.iter().any(|other| other.alive && other.color != candidate.color && other.liberties.contains(&liberty)
+                    && is_self_atari(board, liberty, candidate) && is_self_atari(board, liberty, other)) {
+                    shared += 1;
+                } else {
+                    compatible = false;
+                }
+            }
+
+            if !compatible || eyes > 1 || shared == 0 {
+                candidates[i].alive = false;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let mut result = SekiAnalysis {stones: GoBoardMask::new(), shared_liberties: GoBoardMask::new(), eyes: GoBoardMask::new()};
+    for candidate in candidates.iter().filter(|candidate| candidate.alive) {
+        for &stone in &candidate.stones {
+            result.stones.set(stone, true);
+        }
+        for &liberty in &candidate.liberties {
+            if is_true_eye(board, liberty, candidate.color) {
+                result.eyes.set(liberty, true);
+            } else {
+                result.shared_liberties.set(liberty, true);
+            }
+        }
+    }
+    result
+}
+
+fn is_self_atari(board: &GoBoard, liberty: GoCoordinates, candidate: &Candidate) -> bool {
+    match board.try_play(liberty, candidate.color) {
+        Some((new_board, _)) => new_board.liberties(liberty).len() <= 1,
+        None => true,
+    }
+}
+
+pub fn is_in_seki(board: &GoBoard, coordinates: GoCoordinates) -> bool {
+    find_seki(board).is_in_seki(coordinates)
+}
+
+impl SekiAnalysis {
+    pub fn is_in_seki(&self, coordinates: GoCoordinates) -> bool {
+        self.stones.get(coordinates)
+    }
+
+    pub fn stones(&self) -> &GoBoardMask {
+        &self.stones
+    }
+
+    /// The liberties shared between the groups in seki, which belong to nobody.
+    pub fn shared_liberties(&self) -> &GoBoardMask {
+        &self.shared_liberties
+    }
+
+    /// Eyes of groups in seki, which are not territory under Japanese rules.
+    pub fn eyes(&self) -> &GoBoardMask {
+        &self.eyes
+    }
+
+    pub fn is_seki_point(&self, coordinates: GoCoordinates) -> bool {
+        self.shared_liberties.get(coordinates) || self.eyes.get(coordinates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_seki;
+    use crate::go::board::{GoBoard, GoCoordinates};
+
+    #[test]
+    fn test_seki_without_eyes() {
+        let board = GoBoard::from_rows(&[
+            "O.XXX.OX.",
+            "OOOOOOOX.",
+            "XXXXXXXX.",
+        ]);
+
+        let seki = find_seki(&board);
+        assert!(seki.is_in_seki(GoCoordinates::new(3, 0)));
+        assert!(seki.is_in_seki(GoCoordinates::new(0, 0)));
+        assert!(!seki.is_in_seki(GoCoordinates::new(0, 2)));
+        assert!(seki.is_seki_point(GoCoordinates::new(1, 0)));
+        assert!(seki.is_seki_point(GoCoordinates::new(5, 0)));
+    }
+
+    #[test]
+    fn test_no_seki_when_one_side_can_approach() {
+        let board = GoBoard::from_rows(&[
+            "O.XX..OX.",
+            "OOOOOOOX.",
+            "XXXXXXXX.",
+        ]);
+
+        let seki = find_seki(&board);
+        assert!(!seki.is_in_seki(GoCoordinates::new(3, 0)));
+    }
+}
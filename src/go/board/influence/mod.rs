@@ -0,0 +1,203 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+pub const INITIAL_INFLUENCE: i32 = 128;
+pub const TERRITORY_DILATIONS: usize = 5;
+pub const TERRITORY_EROSIONS: usize = 21;
+pub const MOYO_DILATIONS: usize = 5;
+pub const MOYO_EROSIONS: usize = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InfluenceClass {
+    Territory(GoStone),
+    Moyo(GoStone),
+    Neutral,
+}
+
+/// Signed influence per point, positive for black and negative for white.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InfluenceMap {
+    values: Vec<i32>,
+}
+
+/// Bouzy 5/21 territory together with the weaker 5/10 moyo estimate.
+#[derive(Clone, Debug)]
+pub struct TerritoryEstimate {
+    board: GoBoard,
+    territory: InfluenceMap,
+    moyo: InfluenceMap,
+}
+
+impl InfluenceMap {
+    pub fn new(board: &GoBoard) -> Self {
+        let values = (0..9 * 9).map(|i: usize| match board.get_stone(i.into()) {
+            GoStone::BLACK => INITIAL_INFLUENCE,
+            GoStone::WHITE => -INITIAL_INFLUENCE,
+            GoStone::NONE => 0,
+        }).collect();
+        Self {values}
+    }
+
+    pub fn bouzy(board: &GoBoard, dilations: usize, erosions: usize) -> Self {
+        let mut result = Self::new(board);
+        for _ in 0..dilations {
+            result.dilate();
+        }
+        for _ in 0..erosions {
+            result.erode();
+        }
+        result
+    }
+
+    pub fn get(&self, coordinates: GoCoordinates) -> i32 {
+        self.values[usize::from(coordinates)]
+    }
+
+    pub fn owner(&self, coordinates: GoCoordinates) -> GoStone {
+        let value = self.get(coordinates);
+        if value > 0 {
+            GoStone::BLACK
+        } else if value < 0 {
+            GoStone::WHITE
+        } else {
+            GoStone::NONE
+        }
+    }
+
+    pub fn dilate(&mut self) {
+        let old = self.values.clone();
+
+        for i in 0..9 * 9 {
+            let value = old[i];
+            let neighbors = GoCoordinates::from(i).neighbors();
+            let positive = neighbors.iter().filter(|&&n| old[usize::from(n)] > 0).count() as i32;
+            let negative = neighbors.iter().filter(|&&n| old[usize::from(n)] < 0).count() as i32;
+
+            if value >= 0 && negative == 0 {
+                self.values[i] = value + positive;
+            } else if value <= 0 && positive == 0 {
+                self.values[i] = value - negative;
+            }
+        }
+    }
+
+    pub fn erode(&mut self) {
+        let old = self.values.clone();
+
+        for i in 0..9 * 9 {
+            let value = old[i];
+            let neighbors = GoCoordinates::from(i).neighbors();
+
+            if value > 0 {
+                let opposing = neighbors.iter().filter(|&&n| old[usize::from(n)] <= 0).count() as i32;
+                self.values[i] = (value - opposing).max(0);
+            } else if value < 0 {
+                let opposing = neighbors.iter().filter(|&&n| old[usize::from(n)] >= 0).count() as i32;
+                self.values[i] = (value + opposing).min(0);
+            }
+        }
+    }
+}
+
+impl TerritoryEstimate {
+    pub fn new(board: &GoBoard) -> Self {
+        Self {
+            board: board.clone(),
+            territory: InfluenceMap::bouzy(board, TERRITORY_DILATIONS, TERRITORY_EROSIONS),
+            moyo: InfluenceMap::bouzy(board, MOYO_DILATIONS, MOYO_EROSIONS),
+        }
+    }
+
+    pub fn influence(&self, coordinates: GoCoordinates) -> i32 {
+        self.territory.get(coordinates)
+    }
+
+    pub fn territory_map(&self) -> &InfluenceMap {
+        &self.territory
+    }
+
+    pub fn moyo_map(&self) -> &InfluenceMap {
+        &self.moyo
+    }
+
+    /// Stones of the owning color are never counted as territory or moyo,
+    /// while stones of the other color inside it are considered dead.
+    pub fn classify(&self, coordinates: GoCoordinates) -> InfluenceClass {
+        let stone = self.board.get_stone(coordinates);
+
+        let owner = self.territory.owner(coordinates);
+        if !owner.is_none() {
+            return if stone == owner {InfluenceClass::Neutral} else {InfluenceClass::Territory(owner)};
+        }
+
+        let owner = self.moyo.owner(coordinates);
+        if !owner.is_none() && stone != owner {
+            return InfluenceClass::Moyo(owner);
+        }
+
+        InfluenceClass::Neutral
+    }
+
+    pub fn count(&self, class: InfluenceClass) -> u64 {
+        let mut result = 0;
+        for i in 0..9 * 9 {
+            if self.classify(i.into()) == class {
+                result += 1;
+            }
+        }
+        result
+    }
+
+    /// Area estimate: stones plus territory for each color.
+    pub fn score(&self) -> (u64, u64) {
+        let black = self.board.count(GoStone::BLACK) as u64 + self.count(InfluenceClass::Territory(GoStone::BLACK))
+            - self.dead_stones(GoStone::BLACK);
+        let white = self.board.count(GoStone::WHITE) as u64 + self.count(InfluenceClass::Territory(GoStone::WHITE))
+            - self.dead_stones(GoStone::WHITE);
+        (black, white)
+    }
+
+    fn dead_stones(&self, color: GoStone) -> u64 {
+        let mut result = 0;
+        for i in 0..9 * 9 {
+            let coordinates = GoCoordinates::from(i);
+            if self.board.get_stone(coordinates) == color
+                && self.classify(coordinates) == InfluenceClass::Territory(color.opponent_color()) {
+                result += 1;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InfluenceClass, TerritoryEstimate};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_empty_board_is_neutral() {
+        let estimate = TerritoryEstimate::new(&GoBoard::new());
+        assert_eq!(estimate.count(InfluenceClass::Neutral), 81);
+        assert_eq!(estimate.score(), (0, 0));
+    }
+
+    #[test]
+    fn test_walls_split_the_board() {
+        let mut board = GoBoard::new();
+        for y in 0..9 {
+            board.set_stone(GoCoordinates::new(2, y), GoStone::BLACK);
+            board.set_stone(GoCoordinates::new(6, y), GoStone::WHITE);
+        }
+
+        let estimate = TerritoryEstimate::new(&board);
+        assert_eq!(estimate.classify(GoCoordinates::new(0, 4)), InfluenceClass::Territory(GoStone::BLACK));
+        assert_eq!(estimate.classify(GoCoordinates::new(8, 4)), InfluenceClass::Territory(GoStone::WHITE));
+        assert_eq!(estimate.classify(GoCoordinates::new(2, 4)), InfluenceClass::Neutral);
+        assert!(estimate.influence(GoCoordinates::new(1, 4)) > 0);
+        assert!(estimate.influence(GoCoordinates::new(7, 4)) < 0);
+
+        let (black, white) = estimate.score();
+        assert!(black >= 27);
+        assert!(white >= 27);
+    }
+}
@@ -1,26 +1,32 @@
 use tritvector::{TritVector, Trit};
+use influence::TerritoryEstimate;
 use bitvector::BitVector;
 use std::collections::VecDeque;
+use std::fmt;
 
 pub mod tritvector;
+pub mod influence;
+pub mod analysis;
+pub mod symmetry;
+pub mod zobrist;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum GoStone {
     NONE, BLACK, WHITE
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub struct GoCoordinates {
     x: u8,
     y: u8,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct GoBoard {
     board: TritVector,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct GoBoardMask {
     mask: BitVector,
 }
@@ -59,6 +65,21 @@ impl GoBoard {
         Self {board: TritVector::new(9 * 9)}
     }
 
+    /// Parses rows of `X` for black, `O` for white and any other character for empty points.
+    pub fn from_rows(rows: &[&str]) -> Self {
+        let mut result = Self::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, stone) in row.chars().enumerate() {
+                match stone {
+                    'X' => result.set_stone(GoCoordinates::new_usize(x, y), GoStone::BLACK),
+                    'O' => result.set_stone(GoCoordinates::new_usize(x, y), GoStone::WHITE),
+                    _ => {}
+                }
+            }
+        }
+        result
+    }
+
     pub fn get_stone(&self, coordinates: GoCoordinates) -> GoStone {
         self.board.get(coordinates.into()).into()
     }
@@ -67,6 +88,25 @@ impl GoBoard {
         self.board.set(coordinates.into(), stone.into());
     }
 
+    /// Returns the board after `color` played at `coordinates` together with
+    /// the number of captured stones, or `None` for occupied points and suicide.
+    pub fn try_play(&self, coordinates: GoCoordinates, color: GoStone) -> Option<(GoBoard, u64)> {
+        assert!(!color.is_none());
+        if !self.get_stone(coordinates).is_none() {
+            return None;
+        }
+
+        let mut new_board = self.clone();
+        new_board.set_stone(coordinates, color);
+        let killed_stones = new_board.kill_stones(coordinates);
+
+        if new_board.group_has_liberties(coordinates) {
+            Some((new_board, killed_stones))
+        } else {
+            None
+        }
+    }
+
     pub fn kill_stones(&mut self, coordinates: GoCoordinates) -> u64 {
         let killer = self.get_stone(coordinates);
         let killed = killer.opponent_color();
@@ -111,6 +151,75 @@ impl GoBoard {
         return false;
     }
 
+    /// The connected points of the same color as `coordinates`, which is
+    /// the surrounding empty region for an empty point.
+    pub fn group(&self, coordinates: GoCoordinates) -> Vec<GoCoordinates> {
+        let color = self.get_stone(coordinates);
+
+        let mut enqueued = GoBoardMask::new();
+        enqueued.set(coordinates, true);
+
+        let mut queue = VecDeque::new();
+        queue.push_back(coordinates);
+        let mut result = Vec::new();
+
+        while let Some(coordinates) = queue.pop_back() {
+            result.push(coordinates);
+
+            for neighbor in coordinates.neighbors() {
+                if self.get_stone(neighbor) == color && !enqueued.get(neighbor) {
+                    enqueued.set(neighbor, true);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        result
+    }
+
+    pub fn liberties(&self, coordinates: GoCoordinates) -> Vec<GoCoordinates> {
+        let mut counted = GoBoardMask::new();
+        let mut result = Vec::new();
+
+        for stone in self.group(coordinates) {
+            for neighbor in stone.neighbors() {
+                if self.get_stone(neighbor).is_none() && !counted.get(neighbor) {
+                    counted.set(neighbor, true);
+                    result.push(neighbor);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// The color of the stone at `coordinates`, or for an empty point the
+    /// color of the stones that solely surround its region.
+    pub fn area_owner(&self, coordinates: GoCoordinates) -> GoStone {
+        let color = self.get_stone(coordinates);
+        if !color.is_none() {
+            return color;
+        }
+
+        let mut black = false;
+        let mut white = false;
+        for point in self.group(coordinates) {
+            for neighbor in point.neighbors() {
+                match self.get_stone(neighbor) {
+                    GoStone::BLACK => black = true,
+                    GoStone::WHITE => white = true,
+                    GoStone::NONE => {}
+                }
+            }
+        }
+
+        match (black, white) {
+            (true, false) => GoStone::BLACK,
+            (false, true) => GoStone::WHITE,
+            _ => GoStone::NONE,
+        }
+    }
+
     pub fn remove_group(&mut self, coordinates: GoCoordinates) -> u64 {
         let color = self.get_stone(coordinates);
         assert!(!color.is_none());
@@ -137,7 +246,9 @@ impl GoBoard {
         result
     }
 
-    pub fn voronoi_score(&self) -> (u64, u64) {
+    /// The board after growing both colors with `grow_both` until neither can grow: every point
+    /// gets the color of the nearest stones, and points as near to both stay empty.
+    pub fn voronoi(&self) -> GoBoard {
         let mut grow = self.clone();
 
         loop {
@@ -148,9 +259,18 @@ impl GoBoard {
             }
         }
 
+        grow
+    }
+
+    pub fn voronoi_score(&self) -> (u64, u64) {
+        let grow = self.voronoi();
         (grow.count(GoStone::BLACK).into(), grow.count(GoStone::WHITE).into())
     }
 
+    pub fn influence_score(&self) -> (u64, u64) {
+        TerritoryEstimate::new(self).score()
+    }
+
     pub fn grow_both(&mut self) -> bool {
         let mask = GoBoardMask::new_stone_mask(self, GoStone::NONE);
         let mut has_grown = false;
@@ -240,6 +360,18 @@ impl GoCoordinates {
         Self {x: x as u8, y: y as u8}
     }
 
+    /// Parses a point as written by `Display`, such as `C7`, in either case.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut chars = text.chars();
+        let column = chars.next()?.to_ascii_uppercase();
+        let x = COLUMNS.iter().position(|&c| c as char == column)?;
+        let row: usize = chars.as_str().parse().ok()?;
+        if !(1..=9).contains(&row) {
+            return None;
+        }
+        Some(Self::new_usize(x, 9 - row))
+    }
+
     pub fn neighbors(&self) -> Vec<GoCoordinates> {
         let mut result = Vec::new();
         if self.x > 0 {
@@ -257,6 +389,18 @@ impl GoCoordinates {
         result
     }
 
+    pub fn diagonals(&self) -> Vec<GoCoordinates> {
+        let mut result = Vec::new();
+        for &(dx, dy) in &[(-1i8, -1i8), (1, -1), (-1, 1), (1, 1)] {
+            let x = self.x as i8 + dx;
+            let y = self.y as i8 + dy;
+            if (0..9).contains(&x) && (0..9).contains(&y) {
+                result.push(GoCoordinates {x: x as u8, y: y as u8});
+            }
+        }
+        result
+    }
+
     pub fn x(&self) -> u8 {
         self.x
     }
@@ -297,6 +441,16 @@ impl From<GoCoordinates> for usize {
     }
 }
 
+/// The column letters of the usual notation, which skips `I`.
+const COLUMNS: &[u8; 9] = b"ABCDEFGHJ";
+
+/// Formats like `C7`, with the columns from the left and the rows counted from the bottom.
+impl fmt::Display for GoCoordinates {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", COLUMNS[self.x as usize] as char, 9 - self.y)
+    }
+}
+
 impl From<GoStone> for Trit {
     fn from(go_stone: GoStone) -> Self {
         match go_stone {
@@ -319,7 +473,7 @@ impl From<Trit> for GoStone {
 
 #[cfg(test)]
 mod tests {
-    use super::GoCoordinates;
+    use super::{GoBoard, GoCoordinates, GoStone};
 
     #[test]
     fn test_coordinate_transformation() {
@@ -327,4 +481,27 @@ mod tests {
             assert_eq!(i, usize::from(GoCoordinates::from(i)));
         }
     }
+
+    #[test]
+    fn test_voronoi() {
+        let mut board = GoBoard::new();
+        board.set_stone(GoCoordinates::new(0, 4), GoStone::BLACK);
+        board.set_stone(GoCoordinates::new(8, 4), GoStone::WHITE);
+        let voronoi = board.voronoi();
+
+        assert_eq!(voronoi.get_stone(GoCoordinates::new(3, 0)), GoStone::BLACK);
+        assert_eq!(voronoi.get_stone(GoCoordinates::new(4, 8)), GoStone::NONE);
+        assert_eq!(voronoi.get_stone(GoCoordinates::new(5, 2)), GoStone::WHITE);
+        assert_eq!(board.voronoi_score(), (36, 36));
+    }
+
+    #[test]
+    fn test_coordinate_notation() {
+        assert_eq!(GoCoordinates::new(0, 8).to_string(), "A1");
+        assert_eq!(GoCoordinates::new(2, 2).to_string(), "C7");
+        assert_eq!(GoCoordinates::new(8, 0).to_string(), "J9");
+        assert_eq!(GoCoordinates::parse("c7"), Some(GoCoordinates::new(2, 2)));
+        assert_eq!(GoCoordinates::parse("I5"), None);
+        assert_eq!(GoCoordinates::parse("A10"), None);
+    }
 }
\ No newline at end of file
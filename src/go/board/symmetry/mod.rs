@@ -0,0 +1,181 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+
+/// The 8 symmetries of the square board. Rotations are clockwise as drawn, with y growing downwards.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    Transpose,
+    AntiTranspose,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity, Symmetry::Rotate90, Symmetry::Rotate180, Symmetry::Rotate270,
+        Symmetry::FlipHorizontal, Symmetry::FlipVertical, Symmetry::Transpose, Symmetry::AntiTranspose,
+    ];
+
+    pub fn inverse(self) -> Self {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    pub fn apply(self, coordinates: GoCoordinates) -> GoCoordinates {
+        let (x, y) = (coordinates.x(), coordinates.y());
+        let (x, y) = match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (8 - y, x),
+            Symmetry::Rotate180 => (8 - x, 8 - y),
+            Symmetry::Rotate270 => (y, 8 - x),
+            Symmetry::FlipHorizontal => (8 - x, y),
+            Symmetry::FlipVertical => (x, 8 - y),
+            Symmetry::Transpose => (y, x),
+            Symmetry::AntiTranspose => (8 - y, 8 - x),
+        };
+        GoCoordinates::new(x, y)
+    }
+
+    /// Transforms a relative offset between two points.
+    pub fn apply_offset(self, dx: i8, dy: i8) -> (i8, i8) {
+        match self {
+            Symmetry::Identity => (dx, dy),
+            Symmetry::Rotate90 => (-dy, dx),
+            Symmetry::Rotate180 => (-dx, -dy),
+            Symmetry::Rotate270 => (dy, -dx),
+            Symmetry::FlipHorizontal => (-dx, dy),
+            Symmetry::FlipVertical => (dx, -dy),
+            Symmetry::Transpose => (dy, dx),
+            Symmetry::AntiTranspose => (-dy, -dx),
+        }
+    }
+
+    /// Transforms a move, where `None` is a pass and stays one.
+    pub fn apply_move(self, game_move: Option<GoCoordinates>) -> Option<GoCoordinates> {
+        game_move.map(|coordinates| self.apply(coordinates))
+    }
+
+    pub fn apply_board(self, board: &GoBoard) -> GoBoard {
+        let mut result = GoBoard::new();
+        for i in 0..9 * 9 {
+            let coordinates = GoCoordinates::from(i);
+            result.set_stone(self.apply(coordinates), board.get_stone(coordinates));
+        }
+        result
+    }
+
+    pub fn apply_mask(self, mask: &GoBoardMask) -> GoBoardMask {
+        let mut result = GoBoardMask::new();
+        for i in 0..9 * 9 {
+            let coordinates = GoCoordinates::from(i);
+            result.set(self.apply(coordinates), mask.get(coordinates));
+        }
+        result
+    }
+
+    /// Moves the values of one plane of 81 points in row order.
+    pub fn apply_plane<T: Copy + Default>(self, plane: &[T]) -> Vec<T> {
+        assert_eq!(plane.len(), 9 * 9);
+        let mut result = vec![T::default(); 9 * 9];
+        for (i, &value) in plane.iter().enumerate() {
+            result[usize::from(self.apply(GoCoordinates::from(i)))] = value;
+        }
+        result
+    }
+}
+
+/// The variant of `board` that comes first when the points are compared in index order,
+/// empty before black before white, with the symmetry that maps `board` to it.
+/// Ties keep the first symmetry in `Symmetry::ALL`.
+pub fn canonical_form(board: &GoBoard) -> (GoBoard, Symmetry) {
+    let mut best = (board.clone(), Symmetry::Identity);
+    let mut best_key = board_key(board);
+
+    for &symmetry in &Symmetry::ALL[1..] {
+        let transformed = symmetry.apply_board(board);
+        let key = board_key(&transformed);
+        if key < best_key {
+            best_key = key;
+            best = (transformed, symmetry);
+        }
+    }
+    best
+}
+
+fn board_key(board: &GoBoard) -> Vec<u8> {
+    (0..9 * 9)
+        .map(|i| match board.get_stone(GoCoordinates::from(i)) {
+            GoStone::NONE => 0,
+            GoStone::BLACK => 1,
+            GoStone::WHITE => 2,
+        })
+        .collect()
+}
+
+/// The symmetries that map `board` onto itself.
+pub fn automorphisms(board: &GoBoard) -> Vec<Symmetry> {
+    Symmetry::ALL.iter().copied().filter(|symmetry| symmetry.apply_board(board) == *board).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{automorphisms, canonical_form, Symmetry};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_symmetries_are_distinct_and_invertible() {
+        let point = GoCoordinates::new(1, 3);
+        let mut images = Vec::new();
+        for &symmetry in &Symmetry::ALL {
+            for i in 0..9 * 9 {
+                let coordinates = GoCoordinates::from(i);
+                assert_eq!(symmetry.inverse().apply(symmetry.apply(coordinates)), coordinates);
+            }
+            images.push(symmetry.apply(point));
+        }
+        for image in &images {
+            assert_eq!(images.iter().filter(|other| *other == image).count(), 1);
+        }
+        assert_eq!(Symmetry::Rotate90.apply(GoCoordinates::new(0, 0)), GoCoordinates::new(8, 0));
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let board = GoBoard::from_rows(&[
+            ".........",
+            "..X......",
+            "......O..",
+        ]);
+        let (canonical, symmetry) = canonical_form(&board);
+        assert_eq!(symmetry.apply_board(&board), canonical);
+
+        for &symmetry in &Symmetry::ALL {
+            assert_eq!(canonical_form(&symmetry.apply_board(&board)).0, canonical);
+        }
+        assert_eq!(automorphisms(&GoBoard::new()).len(), 8);
+        assert_eq!(automorphisms(&board), vec![Symmetry::Identity]);
+    }
+
+    #[test]
+    fn test_moves_commute_with_symmetries() {
+        let board = GoBoard::from_rows(&[
+            ".X.......",
+            "XO.......",
+            ".X.......",
+        ]);
+        let capture = GoCoordinates::new(2, 1);
+        let (played, _) = board.try_play(capture, GoStone::BLACK).unwrap();
+
+        for &symmetry in &Symmetry::ALL {
+            let (transformed, captured) = symmetry.apply_board(&board).try_play(symmetry.apply(capture), GoStone::BLACK).unwrap();
+            assert_eq!(captured, 1);
+            assert_eq!(transformed, symmetry.apply_board(&played));
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use bitvector::BitVector;
+use std::hash::{Hash, Hasher};
 
 #[derive(Copy, Clone, Debug)]
 pub enum Trit {
@@ -58,4 +59,13 @@ impl PartialEq for TritVector {
     }
 }
 
-impl Eq for TritVector {}
\ No newline at end of file
+impl Eq for TritVector {}
+
+impl Hash for TritVector {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.trit_len.hash(state);
+        for i in 0..self.trit_len * 2 {
+            self.bitvector.contains(i).hash(state);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+/// Xored into the hash when black is to move.
+pub const BLACK_TO_MOVE: u64 = splitmix64(2 * 9 * 9);
+
+/// The SplitMix64 mixing function. Keys are derived from it instead of stored in a
+/// table, so they are the same in every run.
+pub const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// The key of `stone` at `coordinates`, 0 for an empty point.
+pub fn stone_key(coordinates: GoCoordinates, stone: GoStone) -> u64 {
+    let index = usize::from(coordinates) as u64;
+    match stone {
+        GoStone::NONE => 0,
+        GoStone::BLACK => splitmix64(2 * index),
+        GoStone::WHITE => splitmix64(2 * index + 1),
+    }
+}
+
+/// The Zobrist hash of `board` with `to_move` to play. Placing or removing a stone
+/// changes it by `stone_key`, passing by `BLACK_TO_MOVE`.
+pub fn hash(board: &GoBoard, to_move: GoStone) -> u64 {
+    let mut result = if to_move == GoStone::BLACK {BLACK_TO_MOVE} else {0};
+    for i in 0..9 * 9 {
+        let coordinates = GoCoordinates::from(i);
+        result ^= stone_key(coordinates, board.get_stone(coordinates));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash, stone_key, BLACK_TO_MOVE};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_hash_is_incremental() {
+        let board = GoBoard::from_rows(&[".X.", "XOX"]);
+        let (played, _) = board.try_play(GoCoordinates::new(1, 2), GoStone::BLACK).unwrap();
+
+        let expected = hash(&board, GoStone::BLACK) ^ BLACK_TO_MOVE
+            ^ stone_key(GoCoordinates::new(1, 2), GoStone::BLACK)
+            ^ stone_key(GoCoordinates::new(1, 1), GoStone::WHITE);
+        assert_eq!(hash(&played, GoStone::WHITE), expected);
+        assert_ne!(hash(&board, GoStone::BLACK), hash(&board, GoStone::WHITE));
+        assert_eq!(hash(&GoBoard::new(), GoStone::WHITE), 0);
+    }
+}
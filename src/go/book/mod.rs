@@ -0,0 +1,356 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::board::symmetry::{automorphisms, canonical_form, Symmetry};
+use crate::go::ai::{GoAI, MoveAnalysis};
+use crate::go::clock::PlayerClock;
+use crate::go::selfplay::SelfPlayRecord;
+use crate::go::sgf::{format_coordinates, parse_coordinates, SgfError, SgfNode};
+use crate::go::GoGame;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub const DEFAULT_MAX_DEPTH: usize = 12;
+/// How often a move must have been played before `OpeningBook::best_move` picks it.
+pub const DEFAULT_MIN_COUNT: u32 = 2;
+
+/// Opening statistics keyed by the hash of the canonical form of a position and the side to move.
+/// Moves are stored in the orientation of the canonical form, so all symmetric variants of a
+/// position share one entry, and moves that are equivalent on a symmetric position are merged.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookMove>>,
+    max_depth: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BookMove {
+    game_move: Option<GoCoordinates>,
+    count: u32,
+    /// Wins of the player making the move, draws counting half.
+    wins: f32,
+}
+
+/// Plays the book move if there is one and asks `fallback` otherwise.
+pub struct BookAI<A: GoAI> {
+    book: OpeningBook,
+    fallback: A,
+    game: GoGame,
+    min_count: u32,
+    played_from_book: bool,
+}
+
+/// A hash of the canonical form of `board` with `to_move`, and the symmetry that maps `board` to that form.
+pub fn position_key(board: &GoBoard, to_move: GoStone) -> (u64, Symmetry) {
+    let (canonical, symmetry) = canonical_form(board);
+
+    // FNV-1a, so the keys stay the same between runs and builds.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let stones = (0..9 * 9).map(|i| canonical.get_stone(GoCoordinates::from(i)));
+    for stone in stones.chain(std::iter::once(to_move)) {
+        let value = match stone {
+            GoStone::NONE => 0,
+            GoStone::BLACK => 1,
+            GoStone::WHITE => 2,
+        };
+        hash ^= value;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash, symmetry)
+}
+
+/// The representative of `game_move` among the moves that are equivalent on the symmetric `canonical` board.
+fn canonical_move(canonical: &GoBoard, game_move: Option<GoCoordinates>) -> Option<GoCoordinates> {
+    automorphisms(canonical).into_iter()
+        .map(|symmetry| symmetry.apply_move(game_move))
+        .min_by_key(|game_move| game_move.map(usize::from))
+        .unwrap()
+}
+
+impl OpeningBook {
+    /// An empty book that records the first `max_depth` moves of each game.
+    pub fn new(max_depth: usize) -> Self {
+        Self {entries: HashMap::new(), max_depth}
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Counts `game_move` in the position `board` with `to_move` to play.
+    pub fn add_position(&mut self, board: &GoBoard, to_move: GoStone, game_move: Option<GoCoordinates>, winner: GoStone) {
+        let (key, symmetry) = position_key(board, to_move);
+        let game_move = canonical_move(&symmetry.apply_board(board), symmetry.apply_move(game_move));
+        let wins = if winner == to_move {1.0} else if winner.is_none() {0.5} else {0.0};
+
+        let moves = self.entries.entry(key).or_default();
+        match moves.iter_mut().find(|book_move| book_move.game_move == game_move) {
+            Some(book_move) => {
+                book_move.count += 1;
+                book_move.wins += wins;
+            }
+            None => moves.push(BookMove {game_move, count: 1, wins}),
+        }
+    }
+
+    /// Adds the first `max_depth` moves of a game played from the empty board. Stops at an illegal move.
+    pub fn add_game(&mut self, moves: &[Option<GoCoordinates>], winner: GoStone) {
+        let mut game = GoGame::new();
+        for &game_move in moves.iter().take(self.max_depth) {
+            let board = game.current_board().clone();
+            let to_move = game.current_turn();
+            if game.play(game_move).is_err() {
+                break;
+            }
+            self.add_position(&board, to_move, game_move, winner);
+        }
+    }
+
+    /// Adds the main lines of 9x9 games without setup stones and with a known result.
+    pub fn add_sgf(&mut self, root: &SgfNode) -> Result<(), SgfError> {
+        if root.property("SZ").is_some_and(|size| size != "9") || !root.property_values("AB").is_empty() || !root.property_values("AW").is_empty() {
+            return Err(SgfError);
+        }
+        let winner = root.winner().ok_or(SgfError)?;
+        let moves: Vec<_> = root.main_line_moves()?.into_iter().map(|(_, game_move)| game_move).collect();
+        self.add_game(&moves, winner);
+        Ok(())
+    }
+
+    /// Self-play records do not know their move number, so the depth is the number of stones on the board.
+    pub fn add_self_play(&mut self, records: &[SelfPlayRecord]) {
+        for record in records {
+            let board = record.board();
+            if ((board.count(GoStone::BLACK) + board.count(GoStone::WHITE)) as usize) < self.max_depth {
+                self.add_position(board, record.to_move(), record.played(), record.winner());
+            }
+        }
+    }
+
+    /// The recorded moves of the current position of `game`, in its orientation.
+    pub fn lookup(&self, game: &GoGame) -> Vec<BookMove> {
+        let (key, symmetry) = position_key(game.current_board(), game.current_turn());
+        let inverse = symmetry.inverse();
+        self.entries.get(&key).map_or(Vec::new(), |moves| {
+            moves.iter()
+                .map(|&book_move| BookMove {game_move: inverse.apply_move(book_move.game_move), ..book_move})
+                .collect()
+        })
+    }
+
+    /// The legal book move with the best win rate among those played at least `min_count` times.
+    pub fn best_move(&self, game: &GoGame, min_count: u32) -> Option<Option<GoCoordinates>> {
+        self.lookup(game).into_iter()
+            .filter(|book_move| book_move.count >= min_count && book_move.game_move.is_none_or(|c| game.is_legal(c)))
+            .max_by(|a, b| a.win_rate().total_cmp(&b.win_rate()).then(a.count.cmp(&b.count)))
+            .map(|book_move| book_move.game_move)
+    }
+
+    /// Writes the book as text: a `depth` line, then one `key move count wins` line per move.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "depth {}", self.max_depth)?;
+        let mut keys: Vec<_> = self.entries.keys().collect();
+        keys.sort();
+        for key in keys {
+            for book_move in &self.entries[key] {
+                let game_move = match book_move.game_move {
+                    Some(_) => format_coordinates(book_move.game_move),
+                    None => "pass".to_string(),
+                };
+                writeln!(writer, "{:016x} {} {} {}", key, game_move, book_move.count, book_move.wins)?;
+            }
+        }
+        writer.flush()
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut lines = reader.lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+        let max_depth = header.strip_prefix("depth ").and_then(|depth| depth.parse().ok()).ok_or_else(invalid_book)?;
+        let mut result = Self::new(max_depth);
+
+        for line in lines {
+            let (key, book_move) = parse_entry(&line?).ok_or_else(invalid_book)?;
+            result.entries.entry(key).or_default().push(book_move);
+        }
+        Ok(result)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Reads a `key move count wins` line. Moves played zero times are invalid.
+fn parse_entry(line: &str) -> Option<(u64, BookMove)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    let key = u64::from_str_radix(parts[0], 16).ok()?;
+    let game_move = if parts[1] == "pass" {None} else {Some(parse_coordinates(parts[1]).ok()??)};
+    let count = parts[2].parse().ok().filter(|&count| count > 0)?;
+    let wins = parts[3].parse().ok()?;
+    Some((key, BookMove {game_move, count, wins}))
+}
+
+fn invalid_book() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not an opening book")
+}
+
+impl BookMove {
+    pub fn game_move(&self) -> Option<GoCoordinates> {
+        self.game_move
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn win_rate(&self) -> f32 {
+        self.wins / self.count as f32
+    }
+}
+
+impl<A: GoAI> BookAI<A> {
+    pub fn new(book: OpeningBook, fallback: A) -> Self {
+        Self {book, fallback, game: GoGame::new(), min_count: DEFAULT_MIN_COUNT, played_from_book: false}
+    }
+
+    pub fn set_min_count(&mut self, min_count: u32) {
+        self.min_count = min_count;
+    }
+
+    pub fn fallback(&self) -> &A {
+        &self.fallback
+    }
+}
+
+impl<A: GoAI> GoAI for BookAI<A> {
+    fn set_game(&mut self, game: GoGame) {
+        self.fallback.set_game(game.clone());
+        self.game = game;
+    }
+
+    fn best_move(&mut self) -> Option<GoCoordinates> {
+        let book_move = self.book.best_move(&self.game, self.min_count);
+        self.played_from_book = book_move.is_some();
+        match book_move {
+            Some(game_move) => game_move,
+            None => self.fallback.best_move(),
+        }
+    }
+
+    fn visit_counts(&self) -> Vec<(Option<GoCoordinates>, u32)> {
+        self.fallback.visit_counts()
+    }
+
+    fn start_pondering(&mut self) {
+        self.fallback.start_pondering();
+    }
+
+    fn stop_pondering(&mut self) {
+        self.fallback.stop_pondering();
+    }
+
+    fn is_pondering(&self) -> bool {
+        self.fallback.is_pondering()
+    }
+
+    fn set_time_left(&mut self, clock: &PlayerClock) {
+        self.fallback.set_time_left(clock);
+    }
+
+    fn set_komi(&mut self, komi: f32) {
+        self.fallback.set_komi(komi);
+    }
+
+    /// `None` after a book move, since the fallback did not search it.
+    fn win_rate_estimate(&self) -> Option<f32> {
+        if self.played_from_book {
+            None
+        } else {
+            self.fallback.win_rate_estimate()
+        }
+    }
+
+    fn analysis(&self) -> Vec<MoveAnalysis> {
+        self.fallback.analysis()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BookAI, OpeningBook};
+    use crate::go::ai::GoAI;
+    use crate::go::board::{GoCoordinates, GoStone};
+    use crate::go::board::symmetry::Symmetry;
+    use crate::go::sgf::parse;
+    use crate::go::GoGame;
+
+    struct PassAI;
+
+    impl GoAI for PassAI {
+        fn set_game(&mut self, _game: GoGame) {}
+
+        fn best_move(&mut self) -> Option<GoCoordinates> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_symmetric_games_share_entries() {
+        let mut book = OpeningBook::new(2);
+        book.add_sgf(&parse("(;SZ[9]RE[B+3.5];B[ee];W[cc];B[gg])").unwrap()).unwrap();
+        book.add_sgf(&parse("(;SZ[9]RE[W+R];B[ee];W[gg];B[cc])").unwrap()).unwrap();
+        book.add_sgf(&parse("(;SZ[9]RE[W+1.5];B[ee];W[ce])").unwrap()).unwrap();
+
+        let mut game = GoGame::new();
+        let first = book.lookup(&game);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].count(), 3);
+
+        game.play_stone(GoCoordinates::new(4, 4)).unwrap();
+        let replies = book.lookup(&game);
+        assert_eq!(replies.len(), 2);
+        assert!(replies.iter().any(|reply| reply.count() == 2 && reply.win_rate() == 0.5));
+
+        game.play_stone(GoCoordinates::new(2, 2)).unwrap();
+        assert!(book.lookup(&game).is_empty());
+    }
+
+    #[test]
+    fn test_lookup_in_rotated_position() {
+        let mut book = OpeningBook::new(4);
+        for _ in 0..2 {
+            book.add_game(&[Some(GoCoordinates::new(2, 2)), Some(GoCoordinates::new(6, 6))], GoStone::WHITE);
+        }
+
+        let mut game = GoGame::new();
+        game.play_stone(Symmetry::Rotate90.apply(GoCoordinates::new(2, 2))).unwrap();
+        let mut ai = BookAI::new(book.clone(), PassAI);
+        ai.set_game(game.clone());
+        assert_eq!(ai.best_move(), Some(Symmetry::Rotate90.apply(GoCoordinates::new(6, 6))));
+
+        game.play_stone(GoCoordinates::new(0, 0)).unwrap();
+        ai.set_game(game);
+        assert_eq!(ai.best_move(), None);
+
+        let mut text = Vec::new();
+        book.save(&mut text).unwrap();
+        assert_eq!(OpeningBook::load(&text[..]).unwrap(), book);
+        assert!(OpeningBook::load(&b"depth 4\n0000000000000000 C7 0 0\n"[..]).is_err());
+    }
+}
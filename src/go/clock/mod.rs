@@ -0,0 +1,380 @@
+use crate::go::board::GoStone;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The number of moves a player is expected to still play on a 9x9 board, to budget the main time.
+pub const EXPECTED_MOVES_LEFT: u32 = 20;
+/// The part of a byo-yomi period the AI plans to use, to keep a margin for the GUI.
+pub const BYO_YOMI_SAFETY: f32 = 0.8;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeControl {
+    Unlimited,
+    Absolute {main_time: Duration},
+    /// After the main time, `stones` moves have to be played in every period of `period_time`.
+    Canadian {main_time: Duration, period_time: Duration, stones: u32},
+    /// After the main time, every move has to be played within `period_time`; a period
+    /// that runs out is lost and the game is lost on time when all `periods` are gone.
+    Japanese {main_time: Duration, period_time: Duration, periods: u32},
+    /// Every move adds `increment` to the main time.
+    Fischer {main_time: Duration, increment: Duration},
+}
+
+/// The time left of one player.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PlayerClock {
+    control: TimeControl,
+    main_time: Duration,
+    period_time: Duration,
+    periods: u32,
+    stones: u32,
+    flagged: bool,
+}
+
+/// The clocks of both players, where the clock of `to_move` runs since `turn_start`.
+#[derive(Clone, Debug)]
+pub struct GameClock {
+    black: PlayerClock,
+    white: PlayerClock,
+    to_move: GoStone,
+    turn_start: Option<Instant>,
+}
+
+impl TimeControl {
+    /// The time control of the GTP `time_settings` command, which has Canadian byo-yomi.
+    /// A byo-yomi time of zero means absolute time, and byo-yomi without stones no time limit.
+    pub fn from_gtp(main_time: Duration, byo_yomi_time: Duration, byo_yomi_stones: u32) -> Self {
+        if byo_yomi_time == Duration::from_secs(0) {
+            TimeControl::Absolute {main_time}
+        } else if byo_yomi_stones == 0 {
+            TimeControl::Unlimited
+        } else {
+            TimeControl::Canadian {main_time, period_time: byo_yomi_time, stones: byo_yomi_stones}
+        }
+    }
+
+    pub fn main_time(&self) -> Duration {
+        match *self {
+            TimeControl::Unlimited => Duration::from_secs(0),
+            TimeControl::Absolute {main_time} |
+            TimeControl::Canadian {main_time, ..} |
+            TimeControl::Japanese {main_time, ..} |
+            TimeControl::Fischer {main_time, ..} => main_time,
+        }
+    }
+}
+
+impl PlayerClock {
+    /// Canadian byo-yomi without stones is no time limit, as in `TimeControl::from_gtp`.
+    pub fn new(control: TimeControl) -> Self {
+        let control = match control {
+            TimeControl::Canadian {stones: 0, ..} => TimeControl::Unlimited,
+            _ => control,
+        };
+        let (period_time, periods, stones) = match control {
+            TimeControl::Canadian {period_time, stones, ..} => (period_time, 0, stones),
+            TimeControl::Japanese {period_time, periods, ..} => (period_time, periods, 0),
+            _ => (Duration::from_secs(0), 0, 0),
+        };
+        Self {control, main_time: control.main_time(), period_time, periods, stones, flagged: false}
+    }
+
+    pub fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    pub fn main_time(&self) -> Duration {
+        self.main_time
+    }
+
+    /// The time left in the current byo-yomi period.
+    pub fn period_time(&self) -> Duration {
+        self.period_time
+    }
+
+    /// The Japanese byo-yomi periods left.
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    /// The stones left to play in the current Canadian byo-yomi period.
+    pub fn stones(&self) -> u32 {
+        self.stones
+    }
+
+    pub fn is_flagged(&self) -> bool {
+        self.flagged
+    }
+
+    pub fn in_byo_yomi(&self) -> bool {
+        self.main_time == Duration::from_secs(0) && matches!(self.control, TimeControl::Canadian {..} | TimeControl::Japanese {..})
+    }
+
+    /// Uses up `elapsed` of thinking time, first from the main time and then from byo-yomi.
+    pub fn elapse(&mut self, elapsed: Duration) {
+        if self.flagged || self.control == TimeControl::Unlimited {
+            return;
+        }
+
+        let from_main = self.main_time.min(elapsed);
+        self.main_time -= from_main;
+        let mut elapsed = elapsed - from_main;
+        if elapsed == Duration::from_secs(0) {
+            return;
+        }
+
+        match self.control {
+            TimeControl::Canadian {..} => {
+                if elapsed >= self.period_time {
+                    self.period_time = Duration::from_secs(0);
+                    self.flagged = true;
+                } else {
+                    self.period_time -= elapsed;
+                }
+            }
+            TimeControl::Japanese {period_time, ..} => {
+                while self.periods > 0 && elapsed >= self.period_time {
+                    elapsed -= self.period_time;
+                    self.periods -= 1;
+                    self.period_time = period_time;
+                }
+                if self.periods == 0 {
+                    self.period_time = Duration::from_secs(0);
+                    self.flagged = true;
+                } else {
+                    self.period_time -= elapsed;
+                }
+            }
+            _ => self.flagged = true,
+        }
+    }
+
+    /// Finishes a move: resets the byo-yomi period or adds the Fischer increment.
+    pub fn move_played(&mut self) {
+        if self.flagged {
+            return;
+        }
+
+        match self.control {
+            TimeControl::Canadian {period_time, stones, ..} if self.in_byo_yomi() => {
+                self.stones = self.stones.saturating_sub(1);
+                if self.stones == 0 {
+                    self.stones = stones;
+                    self.period_time = period_time;
+                }
+            }
+            TimeControl::Japanese {period_time, ..} if self.in_byo_yomi() => self.period_time = period_time,
+            TimeControl::Fischer {increment, ..} => self.main_time += increment,
+            _ => {}
+        }
+    }
+
+    /// Like the GTP `time_left` command: `stones` of zero sets the main time, otherwise the
+    /// player is in byo-yomi with `time` for the current period and `stones` stones or periods left.
+    pub fn set_time_left(&mut self, time: Duration, stones: u32) {
+        self.flagged = false;
+        if stones == 0 {
+            self.main_time = time;
+            return;
+        }
+
+        self.main_time = Duration::from_secs(0);
+        self.period_time = time;
+        match self.control {
+            TimeControl::Japanese {..} => self.periods = stones,
+            _ => self.stones = stones,
+        }
+    }
+
+    /// How long the player can think about the next move, or `None` without a time limit.
+    pub fn move_budget(&self) -> Option<Duration> {
+        let byo_yomi = match self.control {
+            TimeControl::Unlimited => return None,
+            TimeControl::Absolute {..} => Duration::from_secs(0),
+            TimeControl::Canadian {period_time, stones, ..} => {
+                if self.in_byo_yomi() {
+                    self.period_time / self.stones.max(1)
+                } else {
+                    period_time / stones.max(1)
+                }
+            }
+            TimeControl::Japanese {period_time, ..} => period_time,
+            TimeControl::Fischer {increment, ..} => increment,
+        };
+
+        Some(self.main_time / EXPECTED_MOVES_LEFT + byo_yomi.mul_f32(BYO_YOMI_SAFETY))
+    }
+}
+
+impl fmt::Display for PlayerClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let minutes_seconds = |time: Duration| format!("{}:{:02}", time.as_secs() / 60, time.as_secs() % 60);
+
+        if self.flagged {
+            write!(f, "time out")
+        } else if self.control == TimeControl::Unlimited {
+            write!(f, "-")
+        } else if !self.in_byo_yomi() {
+            write!(f, "{}", minutes_seconds(self.main_time))
+        } else if let TimeControl::Japanese {..} = self.control {
+            write!(f, "{} ({})", minutes_seconds(self.period_time), self.periods)
+        } else {
+            write!(f, "{} / {}", minutes_seconds(self.period_time), self.stones)
+        }
+    }
+}
+
+impl GameClock {
+    pub fn new(control: TimeControl) -> Self {
+        Self {black: PlayerClock::new(control), white: PlayerClock::new(control), to_move: GoStone::BLACK, turn_start: None}
+    }
+
+    pub fn player(&self, color: GoStone) -> &PlayerClock {
+        match color {
+            GoStone::BLACK => &self.black,
+            GoStone::WHITE => &self.white,
+            GoStone::NONE => panic!("Not a player"),
+        }
+    }
+
+    fn player_mut(&mut self, color: GoStone) -> &mut PlayerClock {
+        match color {
+            GoStone::BLACK => &mut self.black,
+            GoStone::WHITE => &mut self.white,
+            GoStone::NONE => panic!("Not a player"),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.turn_start.is_some()
+    }
+
+    /// Stops the running clock without finishing a move, and starts the clock of `color`.
+    pub fn start(&mut self, color: GoStone, now: Instant) {
+        self.stop(now);
+        self.to_move = color;
+        self.turn_start = Some(now);
+    }
+
+    /// Stops the running clock without finishing a move.
+    pub fn stop(&mut self, now: Instant) {
+        if let Some(turn_start) = self.turn_start.take() {
+            let to_move = self.to_move;
+            self.player_mut(to_move).elapse(now.saturating_duration_since(turn_start));
+        }
+    }
+
+    /// The player to move finished a move, so the opponent's clock starts.
+    pub fn press(&mut self, now: Instant) {
+        let to_move = self.to_move;
+        self.stop(now);
+        self.player_mut(to_move).move_played();
+        self.start(to_move.opponent_color(), now);
+    }
+
+    /// The clock of `color` as it is at `now`, including the running move.
+    pub fn current(&self, color: GoStone, now: Instant) -> PlayerClock {
+        let mut result = *self.player(color);
+        if let (Some(turn_start), true) = (self.turn_start, color == self.to_move) {
+            result.elapse(now.saturating_duration_since(turn_start));
+        }
+        result
+    }
+
+    /// The player who ran out of time at `now`, if any.
+    pub fn flagged(&self, now: Instant) -> Option<GoStone> {
+        [GoStone::BLACK, GoStone::WHITE].iter().copied().find(|&color| self.current(color, now).is_flagged())
+    }
+
+    /// The GTP `time_settings` command, which resets both clocks.
+    pub fn time_settings(&mut self, main_time: Duration, byo_yomi_time: Duration, byo_yomi_stones: u32) {
+        let turn_start = self.turn_start;
+        *self = Self::new(TimeControl::from_gtp(main_time, byo_yomi_time, byo_yomi_stones));
+        self.turn_start = turn_start;
+    }
+
+    /// The GTP `time_left` command.
+    pub fn time_left(&mut self, color: GoStone, time: Duration, stones: u32) {
+        self.player_mut(color).set_time_left(time, stones);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameClock, PlayerClock, TimeControl};
+    use crate::go::board::GoStone;
+    use std::time::{Duration, Instant};
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn test_japanese_byo_yomi() {
+        let mut clock = PlayerClock::new(TimeControl::Japanese {main_time: seconds(60), period_time: seconds(30), periods: 3});
+        clock.elapse(seconds(70));
+        clock.move_played();
+        assert!(clock.in_byo_yomi());
+        assert_eq!((clock.period_time(), clock.periods()), (seconds(30), 3));
+
+        clock.elapse(seconds(35));
+        assert_eq!((clock.period_time(), clock.periods()), (seconds(25), 2));
+        clock.move_played();
+        assert_eq!(clock.period_time(), seconds(30));
+
+        clock.elapse(seconds(60));
+        assert!(clock.is_flagged());
+    }
+
+    #[test]
+    fn test_canadian_byo_yomi() {
+        let mut clock = PlayerClock::new(TimeControl::Canadian {main_time: seconds(0), period_time: seconds(60), stones: 2});
+        clock.elapse(seconds(40));
+        clock.move_played();
+        assert_eq!((clock.period_time(), clock.stones()), (seconds(20), 1));
+        clock.elapse(seconds(10));
+        clock.move_played();
+        assert_eq!((clock.period_time(), clock.stones()), (seconds(60), 2));
+
+        clock.elapse(seconds(60));
+        assert!(clock.is_flagged());
+
+        let mut clock = PlayerClock::new(TimeControl::Canadian {main_time: seconds(0), period_time: seconds(60), stones: 0});
+        clock.elapse(seconds(600));
+        clock.move_played();
+        assert_eq!(clock.control(), TimeControl::Unlimited);
+        assert!(!clock.is_flagged());
+    }
+
+    #[test]
+    fn test_fischer_and_absolute() {
+        let mut fischer = PlayerClock::new(TimeControl::Fischer {main_time: seconds(10), increment: seconds(5)});
+        fischer.elapse(seconds(8));
+        fischer.move_played();
+        assert_eq!(fischer.main_time(), seconds(7));
+        fischer.elapse(seconds(8));
+        assert!(fischer.is_flagged());
+
+        let mut absolute = PlayerClock::new(TimeControl::from_gtp(seconds(10), seconds(0), 0));
+        absolute.elapse(seconds(10));
+        assert!(!absolute.is_flagged());
+        absolute.elapse(seconds(1));
+        assert!(absolute.is_flagged());
+    }
+
+    #[test]
+    fn test_game_clock() {
+        let start = Instant::now();
+        let mut clock = GameClock::new(TimeControl::Absolute {main_time: seconds(60)});
+        clock.start(GoStone::BLACK, start);
+        clock.press(start + seconds(20));
+
+        assert_eq!(clock.player(GoStone::BLACK).main_time(), seconds(40));
+        assert_eq!(clock.current(GoStone::WHITE, start + seconds(50)).main_time(), seconds(30));
+        assert_eq!(clock.flagged(start + seconds(50)), None);
+        assert_eq!(clock.flagged(start + seconds(81)), Some(GoStone::WHITE));
+
+        clock.time_left(GoStone::WHITE, seconds(100), 0);
+        assert_eq!(clock.flagged(start + seconds(81)), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! A minimal Go Text Protocol engine, enough for a controller to play timed 9x9 games
+//! against one of the AIs. The controller's `time_settings` and `time_left` drive the clock
+//! the AI budgets its search from.
+
+use crate::go::ai::GoAI;
+use crate::go::board::{GoCoordinates, GoStone};
+use crate::go::clock::{GameClock, TimeControl};
+use crate::go::GoGame;
+use std::time::{Duration, Instant};
+
+pub const COMMANDS: &[&str] = &[
+    "protocol_version", "name", "version", "known_command", "list_commands", "quit",
+    "boardsize", "clear_board", "komi", "play", "genmove", "time_settings", "time_left",
+];
+
+pub struct GtpEngine {
+    ai: Box<dyn GoAI + Send>,
+    game: GoGame,
+    clock: GameClock,
+    quit: bool,
+}
+
+impl GtpEngine {
+    pub fn new(ai: Box<dyn GoAI + Send>) -> Self {
+        Self {ai, game: GoGame::new(), clock: GameClock::new(TimeControl::Unlimited), quit: false}
+    }
+
+    /// Whether the controller sent `quit`.
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Runs one command line and returns the response including its closing empty line,
+    /// or `None` for an empty line or a comment.
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace().peekable();
+        let id = words.next_if(|word| word.parse::<u32>().is_ok()).unwrap_or("");
+        let command = words.next()?;
+        let arguments: Vec<&str> = words.collect();
+
+        Some(match self.run(command, &arguments) {
+            Ok(response) => format!("={} {}\n\n", id, response),
+            Err(message) => format!("?{} {}\n\n", id, message),
+        })
+    }
+
+    fn run(&mut self, command: &str, arguments: &[&str]) -> Result<String, String> {
+        match (command, arguments) {
+            ("protocol_version", []) => Ok("2".to_string()),
+            ("name", []) => Ok("go_ai".to_string()),
+            ("version", []) => Ok(env!("CARGO_PKG_VERSION").to_string()),
+            ("known_command", [name]) => Ok(COMMANDS.contains(name).to_string()),
+            ("list_commands", []) => Ok(COMMANDS.join("\n")),
+            ("quit", []) => {
+                self.quit = true;
+                Ok(String::new())
+            }
+            ("boardsize", [size]) => if *size == "9" {Ok(String::new())} else {Err("unacceptable size".to_string())},
+            ("clear_board", []) => {
+                self.game = GoGame::new();
+                self.clock = GameClock::new(self.clock.player(GoStone::BLACK).control());
+                Ok(String::new())
+            }
+            ("komi", [komi]) => {
+                let komi: f32 = komi.parse().ok().filter(|komi: &f32| komi.is_finite()).ok_or("syntax error")?;
+                self.ai.set_komi(komi);
+                Ok(String::new())
+            }
+            ("play", [color, vertex]) => {
+                let color = parse_color(color).ok_or("syntax error")?;
+                let game_move = parse_vertex(vertex).ok_or("syntax error")?;
+                if color != self.game.current_turn() || self.game.play(game_move).is_err() {
+                    return Err("illegal move".to_string());
+                }
+                if self.clock.is_running() {
+                    self.clock.press(Instant::now());
+                }
+                Ok(String::new())
+            }
+            ("genmove", [color]) => {
+                let color = parse_color(color).ok_or("syntax error")?;
+                if color != self.game.current_turn() || self.game.is_over() {
+                    return Err("illegal move".to_string());
+                }
+                if !self.clock.is_running() {
+                    self.clock.start(color, Instant::now());
+                }
+                self.ai.set_game(self.game.clone());
+                self.ai.set_time_left(&self.clock.current(color, Instant::now()));
+                let game_move = self.ai.best_move();
+                self.game.play(game_move).map_err(|_| "illegal move".to_string())?;
+                self.clock.press(Instant::now());
+                Ok(game_move.map_or("pass".to_string(), |coordinates| coordinates.to_string()))
+            }
+            ("time_settings", [main_time, byo_yomi_time, byo_yomi_stones]) => {
+                let main_time = parse_seconds(main_time).ok_or("syntax error")?;
+                let byo_yomi_time = parse_seconds(byo_yomi_time).ok_or("syntax error")?;
+                let byo_yomi_stones = byo_yomi_stones.parse().map_err(|_| "syntax error")?;
+                self.clock.time_settings(main_time, byo_yomi_time, byo_yomi_stones);
+                Ok(String::new())
+            }
+            ("time_left", [color, time, stones]) => {
+                let color = parse_color(color).ok_or("syntax error")?;
+                let time = parse_seconds(time).ok_or("syntax error")?;
+                let stones = stones.parse().map_err(|_| "syntax error")?;
+                self.clock.time_left(color, time, stones);
+                Ok(String::new())
+            }
+            _ if COMMANDS.contains(&command) => Err("syntax error".to_string()),
+            _ => Err("unknown command".to_string()),
+        }
+    }
+}
+
+fn parse_color(text: &str) -> Option<GoStone> {
+    match text.to_ascii_lowercase().as_str() {
+        "b" | "black" => Some(GoStone::BLACK),
+        "w" | "white" => Some(GoStone::WHITE),
+        _ => None,
+    }
+}
+
+/// A point, or `None` inside the result for a pass.
+fn parse_vertex(text: &str) -> Option<Option<GoCoordinates>> {
+    if text.eq_ignore_ascii_case("pass") {
+        Some(None)
+    } else {
+        GoCoordinates::parse(text).map(Some)
+    }
+}
+
+fn parse_seconds(text: &str) -> Option<Duration> {
+    text.parse().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GtpEngine;
+    use crate::go::ai::mcts_ai::MctsAI;
+    use crate::go::board::GoStone;
+    use crate::go::clock::TimeControl;
+    use std::time::Duration;
+
+    #[test]
+    fn test_play_and_genmove() {
+        let mut engine = GtpEngine::new(Box::new(MctsAI::with_seed(1, 50)));
+        assert_eq!(engine.execute("1 protocol_version").unwrap(), "=1 2\n\n");
+        assert_eq!(engine.execute("# a comment"), None);
+        assert_eq!(engine.execute("boardsize 19").unwrap(), "? unacceptable size\n\n");
+        assert_eq!(engine.execute("play b e5").unwrap(), "= \n\n");
+        assert_eq!(engine.execute("play w e5").unwrap(), "? illegal move\n\n");
+        assert_eq!(engine.execute("frobnicate").unwrap(), "? unknown command\n\n");
+
+        let response = engine.execute("genmove w").unwrap();
+        assert!(response.starts_with("= ") && response.ends_with("\n\n"));
+        assert_eq!(engine.game.moves().len(), 2);
+        assert_eq!(engine.game.current_turn(), GoStone::BLACK);
+
+        engine.execute("quit");
+        assert!(engine.has_quit());
+    }
+
+    #[test]
+    fn test_time_commands_drive_the_clock() {
+        let mut engine = GtpEngine::new(Box::new(MctsAI::with_seed(1, 50)));
+        engine.execute("time_settings 300 30 5");
+        assert_eq!(engine.clock.player(GoStone::BLACK).control(),
+            TimeControl::Canadian {main_time: Duration::from_secs(300), period_time: Duration::from_secs(30), stones: 5});
+
+        engine.execute("time_left white 20 3");
+        let white = engine.clock.player(GoStone::WHITE);
+        assert_eq!((white.period_time(), white.stones()), (Duration::from_secs(20), 3));
+    }
+}
@@ -1,20 +1,57 @@
 use board::{GoStone, GoCoordinates, GoBoard};
+use scoring::GameScore;
+use std::fmt;
 
 pub mod board;
 pub mod ai;
+pub mod book;
+pub mod clock;
+pub mod gtp;
+pub mod nn;
+pub mod pattern;
+pub mod playout;
+pub mod scoring;
+pub mod selfplay;
+pub mod sgf;
+pub mod tsumego;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct GoGame {
     board: Vec<GoBoard>,
+    moves: Vec<Option<GoCoordinates>>,
     turn: u64,
     black_captures: u64,
     white_captures: u64,
+    result: Option<GameResult>,
+}
+
+/// The 9x9 handicap points in the order they are filled: the two opposite corner
+/// star points, the other two and then the center.
+pub const HANDICAP_POINTS: [(u8, u8); 5] = [(6, 2), (2, 6), (6, 6), (2, 2), (4, 4)];
+
+/// How a game ended before scoring, with the winner.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameResult {
+    Resignation(GoStone),
+    Time(GoStone),
+    /// The winner, `GoStone::NONE` for a draw, and the margin in half points.
+    Score(GoStone, u32),
+}
+
+/// Why the current player may not play a stone at a point.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IllegalMove {
+    GameOver,
+    Occupied,
+    Suicide,
+    /// The move would repeat an earlier position.
+    Superko,
 }
 
 impl GoGame {
     pub fn new() -> Self {
         let board = GoBoard::new();
-        Self { board: vec![board], turn: 0, black_captures: 0, white_captures: 0 }
+        Self { board: vec![board], moves: Vec::new(), turn: 0, black_captures: 0, white_captures: 0, result: None }
     }
 
     pub fn from_board(board: GoBoard, current_turn: GoStone) -> Self {
@@ -22,40 +59,123 @@ impl GoGame {
 
         Self {
             board: vec![board],
+            moves: Vec::new(),
             turn: if current_turn == GoStone::BLACK {0} else {1},
             black_captures: 0,
             white_captures: 0,
+            result: None,
+        }
+    }
+
+    /// A game where black starts with `stones` handicap stones and white moves first.
+    /// No stones or a single stone give a normal game; more than five are not supported.
+    pub fn with_handicap(stones: usize) -> Option<Self> {
+        if stones > HANDICAP_POINTS.len() {
+            return None;
+        }
+        if stones < 2 {
+            return Some(Self::new());
         }
+
+        let mut board = GoBoard::new();
+        for &(x, y) in &HANDICAP_POINTS[..stones] {
+            board.set_stone(GoCoordinates::new(x, y), GoStone::BLACK);
+        }
+        Some(Self::from_board(board, GoStone::WHITE))
+    }
+
+    /// Whether the current player may play at `coordinates`, without changing the game.
+    pub fn is_legal(&self, coordinates: GoCoordinates) -> bool {
+        match self.current_board().try_play(coordinates, self.current_turn()) {
+            Some((new_board, _)) => !self.board.contains(&new_board),
+            None => false,
+        }
+    }
+
+    /// The stones the current player would capture by playing at `coordinates`, or why the
+    /// move is illegal, without changing the game.
+    pub fn check_move(&self, coordinates: GoCoordinates) -> Result<Vec<GoCoordinates>, IllegalMove> {
+        if self.is_over() {
+            return Err(IllegalMove::GameOver);
+        }
+        let board = self.current_board();
+        if !board.get_stone(coordinates).is_none() {
+            return Err(IllegalMove::Occupied);
+        }
+        let (new_board, _) = board.try_play(coordinates, self.current_turn()).ok_or(IllegalMove::Suicide)?;
+        if self.board.contains(&new_board) {
+            return Err(IllegalMove::Superko);
+        }
+
+        let opponent = self.current_turn().opponent_color();
+        Ok((0..9 * 9).map(GoCoordinates::from)
+            .filter(|&c| board.get_stone(c) == opponent && new_board.get_stone(c).is_none())
+            .collect())
+    }
+
+    /// The points where the current player may not play only because it would repeat an earlier position.
+    pub fn ko_points(&self) -> Vec<GoCoordinates> {
+        (0..9 * 9).map(GoCoordinates::from).filter(|&c| self.check_move(c) == Err(IllegalMove::Superko)).collect()
     }
 
     pub fn play_stone(&mut self, coordinates: GoCoordinates) -> Result<(), ()> {
-        let current_board = self.current_board();
-        if current_board.get_stone(coordinates) != GoStone::NONE {
+        if self.is_over() {
             return Err(());
         }
+        let (new_board, killed_stones) = self.current_board().try_play(coordinates, self.current_turn()).ok_or(())?;
+
+        if self.board.contains(&new_board) {
+            return Err(())
+        }
 
-        let mut new_board = current_board.clone();
-        new_board.set_stone(coordinates, self.current_turn());
-        let killed_stones = new_board.kill_stones(coordinates);
+        if self.current_turn() == GoStone::BLACK {
+            self.black_captures += killed_stones;
+        } else {
+            self.white_captures += killed_stones;
+        }
+        self.board.push(new_board);
+        self.moves.push(Some(coordinates));
+        self.turn += 1;
+        Ok(())
+    }
 
-        if new_board.group_has_liberties(coordinates) {
-            if self.board.contains(&new_board) {
-                return Err(())
-            }
+    pub fn pass(&mut self) {
+        let board = self.current_board().clone();
+        self.board.push(board);
+        self.moves.push(None);
+        self.turn += 1;
+    }
 
-            if self.current_turn() == GoStone::BLACK {
-                self.black_captures += killed_stones;
-            } else {
-                self.white_captures += killed_stones;
+    /// Plays a stone, or passes for `None`.
+    pub fn play(&mut self, game_move: Option<GoCoordinates>) -> Result<(), IllegalMove> {
+        match game_move {
+            Some(coordinates) => self.play_stone(coordinates).map_err(|_| self.check_move(coordinates).unwrap_err()),
+            None if self.is_over() => Err(IllegalMove::GameOver),
+            None => {
+                self.pass();
+                Ok(())
             }
-            self.board.push(new_board);
-            self.turn += 1;
-            Ok(())
-        } else {
-            Err(())
         }
     }
 
+    /// The moves played since the starting position, where `None` is a pass.
+    pub fn moves(&self) -> &[Option<GoCoordinates>] {
+        &self.moves
+    }
+
+    pub fn last_move(&self) -> Option<Option<GoCoordinates>> {
+        self.moves.last().copied()
+    }
+
+    pub fn consecutive_passes(&self) -> usize {
+        self.moves.iter().rev().take_while(|game_move| game_move.is_none()).count()
+    }
+
+    /// The positions of the game from the starting position to the current one.
+    pub fn history(&self) -> &[GoBoard] {
+        &self.board
+    }
+
     pub fn current_turn(&self) -> GoStone {
         if self.turn % 2 == 0 {GoStone::BLACK} else {GoStone::WHITE}
     }
@@ -64,9 +184,18 @@ impl GoGame {
         self.board.last().unwrap()
     }
 
-    pub fn undo(&mut self) -> Result<(), ()> {
+    pub fn previous_board(&self) -> Option<&GoBoard> {
         if self.board.len() > 1 {
+            self.board.get(self.board.len() - 2)
+        } else {
+            None
+        }
+    }
+
+    pub fn undo(&mut self) -> Result<(), ()> {
+        if self.board.len() > 1 && !self.is_over() {
             self.board.pop();
+            self.moves.pop();
             self.turn -= 1;
             Ok(())
         } else {
@@ -81,4 +210,146 @@ impl GoGame {
     pub fn white_captures(&self) -> u64 {
         self.white_captures
     }
+
+    pub fn result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The game as it was after the first `moves` moves, which can be continued as a new branch.
+    pub fn branch(&self, moves: usize) -> Option<Self> {
+        if moves > self.moves.len() {
+            return None;
+        }
+
+        let first_turn = if (self.turn - self.moves.len() as u64).is_multiple_of(2) {GoStone::BLACK} else {GoStone::WHITE};
+        let mut result = Self::from_board(self.board[0].clone(), first_turn);
+        for &game_move in &self.moves[..moves] {
+            result.play(game_move).ok()?;
+        }
+        Some(result)
+    }
+
+    /// Ends the game with the counted `score`, after both players passed.
+    pub fn end_by_score(&mut self, score: &GameScore) {
+        if !self.is_over() {
+            self.result = Some(GameResult::Score(score.winner(), (2.0 * score.margin().abs()).round() as u32));
+        }
+    }
+
+    /// Ends the game because `color` resigned.
+    pub fn resign(&mut self, color: GoStone) {
+        assert!(!color.is_none());
+        if !self.is_over() {
+            self.result = Some(GameResult::Resignation(color.opponent_color()));
+        }
+    }
+
+    /// Ends the game because `color` ran out of time.
+    pub fn lose_on_time(&mut self, color: GoStone) {
+        assert!(!color.is_none());
+        if !self.is_over() {
+            self.result = Some(GameResult::Time(color.opponent_color()));
+        }
+    }
+}
+
+impl GameResult {
+    pub fn winner(&self) -> GoStone {
+        match *self {
+            GameResult::Resignation(winner) | GameResult::Time(winner) | GameResult::Score(winner, _) => winner,
+        }
+    }
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IllegalMove::GameOver => write!(f, "The game is over"),
+            IllegalMove::Occupied => write!(f, "The point is occupied"),
+            IllegalMove::Suicide => write!(f, "Suicide"),
+            IllegalMove::Superko => write!(f, "Repeats an earlier position (ko)"),
+        }
+    }
+}
+
+/// Formats the result like the SGF `RE` property.
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let winner = if self.winner() == GoStone::BLACK {"B"} else {"W"};
+        match *self {
+            GameResult::Resignation(_) => write!(f, "{}+R", winner),
+            GameResult::Time(_) => write!(f, "{}+T", winner),
+            GameResult::Score(GoStone::NONE, _) => write!(f, "0"),
+            GameResult::Score(_, half_points) => write!(f, "{}+{}", winner, half_points as f32 / 2.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameResult, GoGame, IllegalMove};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_branch() {
+        let mut game = GoGame::with_handicap(2).unwrap();
+        game.play_stone(GoCoordinates::new(4, 4)).unwrap();
+        game.play_stone(GoCoordinates::new(3, 4)).unwrap();
+        game.pass();
+        game.resign(GoStone::BLACK);
+
+        let branch = game.branch(1).unwrap();
+        assert_eq!(branch.moves(), &[Some(GoCoordinates::new(4, 4))]);
+        assert_eq!(branch.history(), &game.history()[..2]);
+        assert_eq!(branch.current_turn(), GoStone::BLACK);
+        assert_eq!(branch.result(), None);
+        assert_eq!(game.result(), Some(GameResult::Resignation(GoStone::WHITE)));
+        assert!(game.branch(4).is_none());
+    }
+
+    #[test]
+    fn test_ko_points() {
+        let mut board = GoBoard::new();
+        for &(x, y) in &[(1, 0), (0, 1), (1, 2)] {
+            board.set_stone(GoCoordinates::new(x, y), GoStone::BLACK);
+        }
+        for &(x, y) in &[(2, 0), (3, 1), (2, 2), (1, 1)] {
+            board.set_stone(GoCoordinates::new(x, y), GoStone::WHITE);
+        }
+        let mut game = GoGame::from_board(board, GoStone::BLACK);
+        assert!(game.ko_points().is_empty());
+
+        game.play_stone(GoCoordinates::new(2, 1)).unwrap();
+        assert_eq!(game.ko_points(), vec![GoCoordinates::new(1, 1)]);
+        assert!(!game.is_legal(GoCoordinates::new(1, 1)));
+    }
+
+    #[test]
+    fn test_check_move() {
+        let mut board = GoBoard::new();
+        for &(x, y) in &[(1, 0), (0, 1)] {
+            board.set_stone(GoCoordinates::new(x, y), GoStone::BLACK);
+        }
+        for &(x, y) in &[(2, 0), (1, 1), (0, 2)] {
+            board.set_stone(GoCoordinates::new(x, y), GoStone::WHITE);
+        }
+        let mut game = GoGame::from_board(board, GoStone::BLACK);
+        let history = game.history().to_vec();
+
+        assert_eq!(game.check_move(GoCoordinates::new(0, 0)), Err(IllegalMove::Suicide));
+        assert_eq!(game.check_move(GoCoordinates::new(1, 1)), Err(IllegalMove::Occupied));
+        assert_eq!(game.check_move(GoCoordinates::new(4, 4)), Ok(Vec::new()));
+        game.pass();
+        let mut captured = game.check_move(GoCoordinates::new(0, 0)).unwrap();
+        captured.sort_by_key(|&c| usize::from(c));
+        assert_eq!(captured, vec![GoCoordinates::new(1, 0), GoCoordinates::new(0, 1)]);
+        assert_eq!(&game.history()[..1], &history[..]);
+
+        game.resign(GoStone::BLACK);
+        assert_eq!(game.check_move(GoCoordinates::new(4, 4)), Err(IllegalMove::GameOver));
+    }
 }
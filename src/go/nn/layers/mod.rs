@@ -0,0 +1,219 @@
+use rand::Rng;
+use rand_pcg::Pcg64Mcg;
+
+/// A convolution over the 9x9 board with zero padding, so the output keeps the board size.
+/// Activations are stored plane by plane, each plane in row order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conv2d {
+    pub(crate) inputs: usize,
+    pub(crate) outputs: usize,
+    pub(crate) kernel: usize,
+    /// Indexed by output, input, kernel row, kernel column.
+    pub(crate) weights: Vec<f32>,
+    pub(crate) bias: Vec<f32>,
+}
+
+/// A fully connected layer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Linear {
+    pub(crate) inputs: usize,
+    pub(crate) outputs: usize,
+    /// Indexed by output, input.
+    pub(crate) weights: Vec<f32>,
+    pub(crate) bias: Vec<f32>,
+}
+
+/// He initialisation for a layer with `fan_in` inputs per output.
+pub(crate) fn initial_weights(count: usize, fan_in: usize, random: &mut Pcg64Mcg) -> Vec<f32> {
+    let limit = (6.0 / fan_in as f32).sqrt();
+    (0..count).map(|_| random.gen_range(-limit..limit)).collect()
+}
+
+impl Conv2d {
+    pub fn new(inputs: usize, outputs: usize, kernel: usize, random: &mut Pcg64Mcg) -> Self {
+        assert!(kernel % 2 == 1);
+        let weights = initial_weights(outputs * inputs * kernel * kernel, inputs * kernel * kernel, random);
+        Self {inputs, outputs, kernel, weights, bias: vec![0.0; outputs]}
+    }
+
+    /// A layer with all weights zero.
+    pub fn zeros(inputs: usize, outputs: usize, kernel: usize) -> Self {
+        assert!(kernel % 2 == 1);
+        Self {inputs, outputs, kernel, weights: vec![0.0; outputs * inputs * kernel * kernel], bias: vec![0.0; outputs]}
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        assert_eq!(input.len(), self.inputs * 9 * 9);
+        let radius = (self.kernel / 2) as isize;
+        let mut result = vec![0.0; self.outputs * 9 * 9];
+
+        for output in 0..self.outputs {
+            let plane = &mut result[output * 81..(output + 1) * 81];
+            for value in plane.iter_mut() {
+                *value = self.bias[output];
+            }
+
+            for input_plane in 0..self.inputs {
+                let source = &input[input_plane * 81..(input_plane + 1) * 81];
+                for ky in 0..self.kernel {
+                    for kx in 0..self.kernel {
+                        let weight = self.weights[self.weight_index(output, input_plane, ky, kx)];
+                        let dy = ky as isize - radius;
+                        let dx = kx as isize - radius;
+                        for (y, x) in overlapping_points(dx, dy) {
+                            plane[y * 9 + x] += weight * source[(y as isize + dy) as usize * 9 + (x as isize + dx) as usize];
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Adds the gradients of the parameters to `gradients` and returns the gradient of the input.
+    pub(crate) fn backward(&self, input: &[f32], grad_output: &[f32], gradients: &mut Conv2d) -> Vec<f32> {
+        let radius = (self.kernel / 2) as isize;
+        let mut result = vec![0.0; input.len()];
+
+        for output in 0..self.outputs {
+            let grad_plane = &grad_output[output * 81..(output + 1) * 81];
+            gradients.bias[output] += grad_plane.iter().sum::<f32>();
+
+            for input_plane in 0..self.inputs {
+                let source = &input[input_plane * 81..(input_plane + 1) * 81];
+                for ky in 0..self.kernel {
+                    for kx in 0..self.kernel {
+                        let index = self.weight_index(output, input_plane, ky, kx);
+                        let weight = self.weights[index];
+                        let dy = ky as isize - radius;
+                        let dx = kx as isize - radius;
+                        let mut grad_weight = 0.0;
+                        for (y, x) in overlapping_points(dx, dy) {
+                            let source_index = (y as isize + dy) as usize * 9 + (x as isize + dx) as usize;
+                            grad_weight += grad_plane[y * 9 + x] * source[source_index];
+                            result[input_plane * 81 + source_index] += weight * grad_plane[y * 9 + x];
+                        }
+                        gradients.weights[index] += grad_weight;
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub(crate) fn weight_index(&self, output: usize, input: usize, ky: usize, kx: usize) -> usize {
+        ((output * self.inputs + input) * self.kernel + ky) * self.kernel + kx
+    }
+}
+
+/// The points `(y, x)` for which `(y + dy, x + dx)` is still on the board.
+pub(crate) fn overlapping_points(dx: isize, dy: isize) -> impl Iterator<Item = (usize, usize)> {
+    let ys = (0.max(-dy) as usize)..(9.min(9 - dy) as usize);
+    ys.flat_map(move |y| ((0.max(-dx) as usize)..(9.min(9 - dx) as usize)).map(move |x| (y, x)))
+}
+
+impl Linear {
+    pub fn new(inputs: usize, outputs: usize, random: &mut Pcg64Mcg) -> Self {
+        Self {inputs, outputs, weights: initial_weights(outputs * inputs, inputs, random), bias: vec![0.0; outputs]}
+    }
+
+    /// A layer with all weights zero.
+    pub fn zeros(inputs: usize, outputs: usize) -> Self {
+        Self {inputs, outputs, weights: vec![0.0; outputs * inputs], bias: vec![0.0; outputs]}
+    }
+
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn outputs(&self) -> usize {
+        self.outputs
+    }
+
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        assert_eq!(input.len(), self.inputs);
+        (0..self.outputs)
+            .map(|output| {
+                let row = &self.weights[output * self.inputs..(output + 1) * self.inputs];
+                self.bias[output] + row.iter().zip(input).map(|(w, x)| w * x).sum::<f32>()
+            })
+            .collect()
+    }
+
+    /// Adds the gradients of the parameters to `gradients` and returns the gradient of the input.
+    pub(crate) fn backward(&self, input: &[f32], grad_output: &[f32], gradients: &mut Linear) -> Vec<f32> {
+        let mut result = vec![0.0; self.inputs];
+        for (output, &grad) in grad_output.iter().enumerate() {
+            gradients.bias[output] += grad;
+            let row = output * self.inputs;
+            for (i, &value) in input.iter().enumerate() {
+                gradients.weights[row + i] += grad * value;
+                result[i] += grad * self.weights[row + i];
+            }
+        }
+        result
+    }
+}
+
+pub fn relu(values: &mut [f32]) {
+    for value in values.iter_mut() {
+        *value = value.max(0.0);
+    }
+}
+
+/// Zeroes the gradient wherever the ReLU that produced `output` was inactive.
+pub(crate) fn relu_backward(output: &[f32], gradient: &mut [f32]) {
+    for (grad, &value) in gradient.iter_mut().zip(output) {
+        if value <= 0.0 {
+            *grad = 0.0;
+        }
+    }
+}
+
+pub fn softmax(values: &[f32]) -> Vec<f32> {
+    let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let exponentials: Vec<f32> = values.iter().map(|value| (value - max).exp()).collect();
+    let sum: f32 = exponentials.iter().sum();
+    exponentials.iter().map(|value| value / sum).collect()
+}
+
+pub fn sigmoid(value: f32) -> f32 {
+    1.0 / (1.0 + (-value).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Conv2d, Linear};
+    use rand_pcg::Pcg64Mcg;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_conv_pads_with_zeros() {
+        let mut conv = Conv2d::new(1, 1, 3, &mut Pcg64Mcg::seed_from_u64(0));
+        for weight in conv.weights.iter_mut() {
+            *weight = 1.0;
+        }
+        let output = conv.forward(&vec![1.0; 81]);
+
+        assert_eq!(output[0], 4.0);
+        assert_eq!(output[4], 6.0);
+        assert_eq!(output[4 * 9 + 4], 9.0);
+    }
+
+    #[test]
+    fn test_linear() {
+        let mut linear = Linear::new(2, 1, &mut Pcg64Mcg::seed_from_u64(0));
+        linear.weights = vec![2.0, -1.0];
+        linear.bias = vec![0.5];
+
+        assert_eq!(linear.forward(&[3.0, 1.0]), vec![5.5]);
+    }
+}
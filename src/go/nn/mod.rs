@@ -0,0 +1,312 @@
+//! A small AlphaZero style policy/value network for 9x9, evaluated on the CPU.
+//!
+//! The network is a 3x3 input convolution followed by residual blocks of two 3x3
+//! convolutions, all with ReLU. The policy head is a 1x1 convolution to two planes and
+//! a fully connected layer to the 81 points and pass. The value head is a 1x1
+//! convolution to one plane and two fully connected layers ending in a sigmoid, giving
+//! the probability that the side to move wins.
+//!
+//! # Weights file format
+//!
+//! All numbers are little endian.
+//!
+//! ```text
+//! magic         4 bytes   "GONN"
+//! version       u32       1
+//! input_planes  u32       must equal INPUT_PLANES
+//! channels      u32
+//! blocks        u32
+//! value_hidden  u32
+//! parameters    f32 ...
+//! ```
+//!
+//! The parameters follow layer by layer, each layer writing its weights and then its
+//! biases: the input convolution, the two convolutions of every block, the policy
+//! convolution, the policy layer, the value convolution and the two value layers.
+//! Convolution weights are ordered by output plane, input plane, kernel row and kernel
+//! column, fully connected weights by output and input.
+
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::selfplay::HISTORY_LENGTH;
+use crate::go::GoGame;
+use layers::{initial_weights, relu, sigmoid, softmax, Conv2d, Linear};
+use rand_pcg::Pcg64Mcg;
+use rand::SeedableRng;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+pub mod layers;
+pub mod training;
+
+/// Own and opponent stones for each position of the history, the side to move and a plane of ones.
+pub const INPUT_PLANES: usize = 2 * HISTORY_LENGTH + 2;
+/// The 81 points followed by pass.
+pub const POLICY_SIZE: usize = 9 * 9 + 1;
+pub const PASS_INDEX: usize = 9 * 9;
+
+pub const DEFAULT_CHANNELS: usize = 32;
+pub const DEFAULT_BLOCKS: usize = 3;
+pub const DEFAULT_VALUE_HIDDEN: usize = 64;
+/// The largest sizes `load` accepts, so that a corrupt file can not ask for a huge network.
+pub const MAX_CHANNELS: usize = 128;
+pub const MAX_BLOCKS: usize = 20;
+pub const MAX_VALUE_HIDDEN: usize = 256;
+
+const MAGIC: &[u8; 4] = b"GONN";
+const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    pub(crate) input: Conv2d,
+    pub(crate) blocks: Vec<(Conv2d, Conv2d)>,
+    pub(crate) policy_conv: Conv2d,
+    pub(crate) policy: Linear,
+    pub(crate) value_conv: Conv2d,
+    pub(crate) value_hidden: Linear,
+    pub(crate) value: Linear,
+}
+
+/// Priors of the legal moves, summing to one, and the probability that the side to move wins.
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    pub priors: Vec<(Option<GoCoordinates>, f32)>,
+    pub value: f32,
+}
+
+/// Something that can guide a tree search with move priors and a position value.
+pub trait Evaluator {
+    fn evaluate(&self, game: &GoGame) -> Evaluation;
+}
+
+/// Builds the input planes from `history`, the current position first, as seen by `to_move`.
+pub fn features(history: &[GoBoard], to_move: GoStone) -> Vec<f32> {
+    let mut result = vec![0.0; INPUT_PLANES * 81];
+
+    for (t, board) in history.iter().take(HISTORY_LENGTH).enumerate() {
+        for i in 0..9 * 9 {
+            let stone = board.get_stone(GoCoordinates::from(i));
+            if stone == to_move {
+                result[2 * t * 81 + i] = 1.0;
+            } else if stone == to_move.opponent_color() {
+                result[(2 * t + 1) * 81 + i] = 1.0;
+            }
+        }
+    }
+
+    let color_plane = 2 * HISTORY_LENGTH * 81;
+    result[color_plane..color_plane + 81].fill(if to_move == GoStone::BLACK {1.0} else {0.0});
+    result[color_plane + 81..color_plane + 2 * 81].fill(1.0);
+    result
+}
+
+pub fn game_features(game: &GoGame) -> Vec<f32> {
+    let history: Vec<GoBoard> = game.history().iter().rev().take(HISTORY_LENGTH).cloned().collect();
+    features(&history, game.current_turn())
+}
+
+pub fn policy_index(game_move: Option<GoCoordinates>) -> usize {
+    game_move.map_or(PASS_INDEX, usize::from)
+}
+
+pub fn policy_move(index: usize) -> Option<GoCoordinates> {
+    if index == PASS_INDEX {None} else {Some(GoCoordinates::from(index))}
+}
+
+impl Network {
+    /// A randomly initialised network.
+    pub fn new(channels: usize, blocks: usize, value_hidden: usize, seed: u64) -> Self {
+        let mut random = Pcg64Mcg::seed_from_u64(seed);
+        let mut result = Self::zeros(channels, blocks, value_hidden);
+        for (weights, bias) in result.layers_mut() {
+            *weights = initial_weights(weights.len(), weights.len() / bias.len(), &mut random);
+        }
+        result
+    }
+
+    /// A network with all parameters zero, for `load` to fill in.
+    fn zeros(channels: usize, blocks: usize, value_hidden: usize) -> Self {
+        Self {
+            input: Conv2d::zeros(INPUT_PLANES, channels, 3),
+            blocks: (0..blocks).map(|_| (Conv2d::zeros(channels, channels, 3), Conv2d::zeros(channels, channels, 3))).collect(),
+            policy_conv: Conv2d::zeros(channels, 2, 1),
+            policy: Linear::zeros(2 * 81, POLICY_SIZE),
+            value_conv: Conv2d::zeros(channels, 1, 1),
+            value_hidden: Linear::zeros(81, value_hidden),
+            value: Linear::zeros(value_hidden, 1),
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.input.outputs()
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Policy logits over `POLICY_SIZE` moves and the win probability of the side to move.
+    pub fn forward(&self, features: &[f32]) -> (Vec<f32>, f32) {
+        let mut trunk = self.input.forward(features);
+        relu(&mut trunk);
+
+        for (first, second) in &self.blocks {
+            let mut hidden = first.forward(&trunk);
+            relu(&mut hidden);
+            let mut output = second.forward(&hidden);
+            for (value, residual) in output.iter_mut().zip(&trunk) {
+                *value += residual;
+            }
+            relu(&mut output);
+            trunk = output;
+        }
+
+        let mut policy = self.policy_conv.forward(&trunk);
+        relu(&mut policy);
+        let logits = self.policy.forward(&policy);
+
+        let mut value = self.value_conv.forward(&trunk);
+        relu(&mut value);
+        let mut hidden = self.value_hidden.forward(&value);
+        relu(&mut hidden);
+        (logits, sigmoid(self.value.forward(&hidden)[0]))
+    }
+
+    fn layers(&self) -> Vec<(&Vec<f32>, &Vec<f32>)> {
+        let mut result = vec![(&self.input.weights, &self.input.bias)];
+        for (first, second) in &self.blocks {
+            result.push((&first.weights, &first.bias));
+            result.push((&second.weights, &second.bias));
+        }
+        result.push((&self.policy_conv.weights, &self.policy_conv.bias));
+        result.push((&self.policy.weights, &self.policy.bias));
+        result.push((&self.value_conv.weights, &self.value_conv.bias));
+        result.push((&self.value_hidden.weights, &self.value_hidden.bias));
+        result.push((&self.value.weights, &self.value.bias));
+        result
+    }
+
+    fn layers_mut(&mut self) -> Vec<(&mut Vec<f32>, &mut Vec<f32>)> {
+        let mut result = vec![(&mut self.input.weights, &mut self.input.bias)];
+        for (first, second) in &mut self.blocks {
+            result.push((&mut first.weights, &mut first.bias));
+            result.push((&mut second.weights, &mut second.bias));
+        }
+        result.push((&mut self.policy_conv.weights, &mut self.policy_conv.bias));
+        result.push((&mut self.policy.weights, &mut self.policy.bias));
+        result.push((&mut self.value_conv.weights, &mut self.value_conv.bias));
+        result.push((&mut self.value_hidden.weights, &mut self.value_hidden.bias));
+        result.push((&mut self.value.weights, &mut self.value.bias));
+        result
+    }
+
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        for &value in &[VERSION, INPUT_PLANES as u32, self.channels() as u32, self.blocks() as u32, self.value_hidden.outputs() as u32] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for (weights, bias) in self.layers() {
+            for value in weights.iter().chain(bias.iter()) {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.flush()
+    }
+
+    pub fn load<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || read_u32(&mut reader)? != VERSION || read_u32(&mut reader)? as usize != INPUT_PLANES {
+            return Err(invalid_weights());
+        }
+
+        let channels = read_u32(&mut reader)? as usize;
+        let blocks = read_u32(&mut reader)? as usize;
+        let value_hidden = read_u32(&mut reader)? as usize;
+        if !(1..=MAX_CHANNELS).contains(&channels) || blocks > MAX_BLOCKS || !(1..=MAX_VALUE_HIDDEN).contains(&value_hidden) {
+            return Err(invalid_weights());
+        }
+
+        let mut result = Self::zeros(channels, blocks, value_hidden);
+        for (weights, bias) in result.layers_mut() {
+            for value in weights.iter_mut().chain(bias.iter_mut()) {
+                let mut bytes = [0; 4];
+                reader.read_exact(&mut bytes)?;
+                *value = f32::from_le_bytes(bytes);
+            }
+        }
+
+        if reader.read(&mut [0])? != 0 {
+            return Err(invalid_weights());
+        }
+        Ok(result)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn invalid_weights() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "not a network weights file")
+}
+
+impl Evaluator for Network {
+    /// Masks out illegal moves and renormalises the policy over the rest. Passing is always legal.
+    fn evaluate(&self, game: &GoGame) -> Evaluation {
+        let (logits, value) = self.forward(&game_features(game));
+
+        let legal: Vec<usize> = (0..POLICY_SIZE)
+            .filter(|&index| index == PASS_INDEX || game.is_legal(GoCoordinates::from(index)))
+            .collect();
+        let probabilities = softmax(&legal.iter().map(|&index| logits[index]).collect::<Vec<_>>());
+
+        Evaluation {
+            priors: legal.iter().zip(probabilities).map(|(&index, probability)| (policy_move(index), probability)).collect(),
+            value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Evaluator, Network};
+    use crate::go::board::GoCoordinates;
+    use crate::go::GoGame;
+
+    #[test]
+    fn test_save_and_load() {
+        let network = Network::new(4, 2, 8, 1);
+        let mut bytes = Vec::new();
+        network.save(&mut bytes).unwrap();
+
+        assert_eq!(Network::load(&bytes[..]).unwrap(), network);
+        assert!(Network::load(&bytes[..bytes.len() - 1]).is_err());
+
+        // A header asking for a huge network is rejected before anything is allocated.
+        bytes[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Network::load(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_priors_cover_legal_moves() {
+        let mut game = GoGame::new();
+        game.play_stone(GoCoordinates::new(4, 4)).unwrap();
+
+        let evaluation = Network::new(4, 1, 8, 2).evaluate(&game);
+        assert_eq!(evaluation.priors.len(), 9 * 9);
+        assert!(evaluation.priors.iter().all(|&(game_move, _)| game_move != Some(GoCoordinates::new(4, 4))));
+        assert!((evaluation.priors.iter().map(|&(_, prior)| prior).sum::<f32>() - 1.0).abs() < 1e-4);
+        assert!(evaluation.value > 0.0 && evaluation.value < 1.0);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::go::board::GoStone;
+use crate::go::board::symmetry::Symmetry;
+use crate::go::ai::GoAI;
+use crate::go::ai::puct_ai::PuctAI;
+use crate::go::nn::{features, policy_index, Network, PASS_INDEX, POLICY_SIZE};
+use crate::go::nn::layers::{relu, relu_backward, sigmoid, softmax};
+use crate::go::selfplay::{game_winner, SelfPlayRecord};
+use crate::go::GoGame;
+use rand_pcg::Pcg64Mcg;
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+pub const WEIGHT_DECAY: f32 = 1e-4;
+/// The share of the gating games a new network has to win to replace the old one.
+pub const GATING_THRESHOLD: f32 = 0.55;
+pub const GATING_MAX_MOVES: usize = 3 * 9 * 9;
+/// The opening moves of a gating game that are sampled by visit count, so the games differ.
+pub const GATING_SAMPLING_MOVES: usize = 8;
+
+/// Network inputs with the targets for both heads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingSample {
+    features: Vec<f32>,
+    /// Probabilities over `POLICY_SIZE` moves.
+    policy: Vec<f32>,
+    /// 1 if the side to move won, 0 if it lost and 0.5 for a draw.
+    value: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Optimizer {
+    Sgd {learning_rate: f32, momentum: f32},
+    Adam {learning_rate: f32, beta1: f32, beta2: f32, epsilon: f32},
+}
+
+pub struct Trainer {
+    network: Network,
+    optimizer: Optimizer,
+    first_moment: Network,
+    second_moment: Network,
+    steps: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct GatingResult {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+/// The activations of a forward pass, needed for backpropagation.
+struct ForwardPass {
+    input: Vec<f32>,
+    trunk: Vec<Vec<f32>>,
+    block_hidden: Vec<Vec<f32>>,
+    policy_planes: Vec<f32>,
+    logits: Vec<f32>,
+    value_planes: Vec<f32>,
+    value_hidden: Vec<f32>,
+    value: f32,
+}
+
+impl TrainingSample {
+    pub fn new(features: Vec<f32>, policy: Vec<f32>, value: f32) -> Self {
+        assert_eq!(policy.len(), POLICY_SIZE);
+        Self {features, policy, value}
+    }
+
+    /// The policy target are the visit counts of the search, or the played move if there were none.
+    pub fn from_record(record: &SelfPlayRecord) -> Self {
+        let mut policy = vec![0.0; POLICY_SIZE];
+        let total: u32 = record.visits().iter().map(|&(_, visits)| visits).sum();
+        if total > 0 {
+            for &(game_move, visits) in record.visits() {
+                policy[policy_index(game_move)] += visits as f32 / total as f32;
+            }
+        } else {
+            policy[policy_index(record.played())] = 1.0;
+        }
+
+        let value = if record.winner() == record.to_move() {
+            1.0
+        } else if record.winner().is_none() {
+            0.5
+        } else {
+            0.0
+        };
+
+        Self::new(features(record.history(), record.to_move()), policy, value)
+    }
+
+    pub fn features(&self) -> &[f32] {
+        &self.features
+    }
+
+    pub fn policy(&self) -> &[f32] {
+        &self.policy
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn transformed(&self, symmetry: Symmetry) -> Self {
+        let mut features = Vec::with_capacity(self.features.len());
+        for plane in self.features.chunks(81) {
+            features.extend(symmetry.apply_plane(plane));
+        }
+
+        let mut policy = symmetry.apply_plane(&self.policy[..PASS_INDEX]);
+        policy.push(self.policy[PASS_INDEX]);
+
+        Self {features, policy, value: self.value}
+    }
+}
+
+fn forward(network: &Network, features: &[f32]) -> ForwardPass {
+    let mut trunk = network.input.forward(features);
+    relu(&mut trunk);
+    let mut result_trunk = vec![trunk];
+    let mut block_hidden = Vec::new();
+
+    for (first, second) in &network.blocks {
+        let previous = result_trunk.last().unwrap();
+        let mut hidden = first.forward(previous);
+        relu(&mut hidden);
+        let mut output = second.forward(&hidden);
+        for (value, residual) in output.iter_mut().zip(previous) {
+            *value += residual;
+        }
+        relu(&mut output);
+        block_hidden.push(hidden);
+        result_trunk.push(output);
+    }
+
+    let trunk = result_trunk.last().unwrap();
+    let mut policy_planes = network.policy_conv.forward(trunk);
+    relu(&mut policy_planes);
+    let logits = network.policy.forward(&policy_planes);
+
+    let mut value_planes = network.value_conv.forward(trunk);
+    relu(&mut value_planes);
+    let mut value_hidden = network.value_hidden.forward(&value_planes);
+    relu(&mut value_hidden);
+    let value = network.value.forward(&value_hidden)[0];
+
+    ForwardPass {input: features.to_vec(), trunk: result_trunk, block_hidden, policy_planes, logits, value_planes, value_hidden, value}
+}
+
+/// Cross entropy of the policy plus binary cross entropy of the value.
+fn loss(pass: &ForwardPass, sample: &TrainingSample) -> f32 {
+    let probabilities = softmax(&pass.logits);
+    let policy_loss: f32 = sample.policy.iter().zip(&probabilities)
+        .map(|(target, probability)| -target * probability.max(1e-12).ln())
+        .sum();
+    let value = sigmoid(pass.value).clamp(1e-7, 1.0 - 1e-7);
+    policy_loss - sample.value * value.ln() - (1.0 - sample.value) * (1.0 - value).ln()
+}
+
+/// Adds the gradients of the loss of `sample` to `gradients`.
+fn backward(network: &Network, pass: &ForwardPass, sample: &TrainingSample, gradients: &mut Network) {
+    let mut grad_logits = softmax(&pass.logits);
+    for (grad, target) in grad_logits.iter_mut().zip(&sample.policy) {
+        *grad -= target;
+    }
+    let mut grad_policy = network.policy.backward(&pass.policy_planes, &grad_logits, &mut gradients.policy);
+    relu_backward(&pass.policy_planes, &mut grad_policy);
+    let trunk = pass.trunk.last().unwrap();
+    let mut grad_trunk = network.policy_conv.backward(trunk, &grad_policy, &mut gradients.policy_conv);
+
+    let grad_value = [sigmoid(pass.value) - sample.value];
+    let mut grad_hidden = network.value.backward(&pass.value_hidden, &grad_value, &mut gradients.value);
+    relu_backward(&pass.value_hidden, &mut grad_hidden);
+    let mut grad_planes = network.value_hidden.backward(&pass.value_planes, &grad_hidden, &mut gradients.value_hidden);
+    relu_backward(&pass.value_planes, &mut grad_planes);
+    for (grad, value_grad) in grad_trunk.iter_mut().zip(network.value_conv.backward(trunk, &grad_planes, &mut gradients.value_conv)) {
+        *grad += value_grad;
+    }
+
+    for (index, (first, second)) in network.blocks.iter().enumerate().rev() {
+        relu_backward(&pass.trunk[index + 1], &mut grad_trunk);
+        let (first_gradients, second_gradients) = &mut gradients.blocks[index];
+        let mut grad_hidden = second.backward(&pass.block_hidden[index], &grad_trunk, second_gradients);
+        relu_backward(&pass.block_hidden[index], &mut grad_hidden);
+        for (grad, first_grad) in grad_trunk.iter_mut().zip(first.backward(&pass.trunk[index], &grad_hidden, first_gradients)) {
+            *grad += first_grad;
+        }
+    }
+
+    relu_backward(&pass.trunk[0], &mut grad_trunk);
+    network.input.backward(&pass.input, &grad_trunk, &mut gradients.input);
+}
+
+fn zeroed(network: &Network) -> Network {
+    let mut result = network.clone();
+    for (weights, bias) in result.layers_mut() {
+        for value in weights.iter_mut().chain(bias.iter_mut()) {
+            *value = 0.0;
+        }
+    }
+    result
+}
+
+impl Optimizer {
+    pub fn sgd(learning_rate: f32) -> Self {
+        Optimizer::Sgd {learning_rate, momentum: 0.9}
+    }
+
+    pub fn adam(learning_rate: f32) -> Self {
+        Optimizer::Adam {learning_rate, beta1: 0.9, beta2: 0.999, epsilon: 1e-8}
+    }
+
+    /// Applies the mean `gradients`, with weight decay if `decay` is set.
+    /// `first` and `second` hold the momentum of SGD or the moments of Adam.
+    fn update(self, parameters: &mut [f32], gradients: &[f32], first: &mut [f32], second: &mut [f32], decay: bool, steps: i32) {
+        for (i, parameter) in parameters.iter_mut().enumerate() {
+            let mut grad = gradients[i];
+            if decay {
+                grad += WEIGHT_DECAY * *parameter;
+            }
+
+            match self {
+                Optimizer::Sgd {learning_rate, momentum} => {
+                    first[i] = momentum * first[i] + grad;
+                    *parameter -= learning_rate * first[i];
+                }
+                Optimizer::Adam {learning_rate, beta1, beta2, epsilon} => {
+                    first[i] = beta1 * first[i] + (1.0 - beta1) * grad;
+                    second[i] = beta2 * second[i] + (1.0 - beta2) * grad * grad;
+                    let first_corrected = first[i] / (1.0 - beta1.powi(steps));
+                    let second_corrected = second[i] / (1.0 - beta2.powi(steps));
+                    *parameter -= learning_rate * first_corrected / (second_corrected.sqrt() + epsilon);
+                }
+            }
+        }
+    }
+}
+
+impl Trainer {
+    pub fn new(network: Network, optimizer: Optimizer) -> Self {
+        let first_moment = zeroed(&network);
+        let second_moment = first_moment.clone();
+        Self {network, optimizer, first_moment, second_moment, steps: 0}
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.network
+    }
+
+    pub fn into_network(self) -> Network {
+        self.network
+    }
+
+    /// The mean loss of `samples` without training on them.
+    pub fn loss(&self, samples: &[TrainingSample]) -> f32 {
+        samples.iter().map(|sample| loss(&forward(&self.network, &sample.features), sample)).sum::<f32>() / samples.len() as f32
+    }
+
+    /// One optimizer step on the mean gradient of `samples`. Returns the mean loss before the step.
+    pub fn train_batch(&mut self, samples: &[TrainingSample]) -> f32 {
+        assert!(!samples.is_empty());
+        let mut gradients = zeroed(&self.network);
+        let mut total_loss = 0.0;
+        for sample in samples {
+            let pass = forward(&self.network, &sample.features);
+            total_loss += loss(&pass, sample);
+            backward(&self.network, &pass, sample, &mut gradients);
+        }
+
+        let scale = 1.0 / samples.len() as f32;
+        for (weights, bias) in gradients.layers_mut() {
+            for value in weights.iter_mut().chain(bias.iter_mut()) {
+                *value *= scale;
+            }
+        }
+
+        self.steps += 1;
+        let steps = self.steps as i32;
+        let optimizer = self.optimizer;
+        let layers = self.network.layers_mut().into_iter()
+            .zip(gradients.layers())
+            .zip(self.first_moment.layers_mut().into_iter().zip(self.second_moment.layers_mut()));
+
+        for ((parameters, grads), (first, second)) in layers {
+            optimizer.update(parameters.0, grads.0, first.0, second.0, true, steps);
+            optimizer.update(parameters.1, grads.1, first.1, second.1, false, steps);
+        }
+
+        total_loss * scale
+    }
+
+    /// Trains on shuffled batches of `samples`, each sample under a random symmetry. Returns the mean loss.
+    pub fn train_epoch(&mut self, samples: &[TrainingSample], batch_size: usize, random: &mut Pcg64Mcg) -> f32 {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        order.shuffle(random);
+
+        let mut total_loss = 0.0;
+        let mut batches = 0;
+        for chunk in order.chunks(batch_size.max(1)) {
+            let batch: Vec<TrainingSample> = chunk.iter().map(|&i| samples[i].transformed(Symmetry::ALL[random.gen_range(0..8)])).collect();
+            total_loss += self.train_batch(&batch);
+            batches += 1;
+        }
+        total_loss / batches.max(1) as f32
+    }
+}
+
+impl GatingResult {
+    /// Draws count as half a win.
+    pub fn win_rate(&self) -> f32 {
+        let games = self.wins + self.losses + self.draws;
+        if games == 0 {
+            0.0
+        } else {
+            (self.wins as f32 + 0.5 * self.draws as f32) / games as f32
+        }
+    }
+
+    pub fn promotes(&self) -> bool {
+        self.win_rate() >= GATING_THRESHOLD
+    }
+}
+
+/// Plays `games` games between `candidate` and `best`, alternating colors, and counts them from the candidate's side.
+/// Both players explore with seeds derived from `seed` and the game number.
+pub fn gating_match(candidate: &Network, best: &Network, games: u32, visits: usize, komi: f32, seed: u64) -> GatingResult {
+    let mut result = GatingResult::default();
+    for index in 0..games {
+        let mut candidate_ai = PuctAI::with_visits(candidate.clone(), visits);
+        let mut best_ai = PuctAI::with_visits(best.clone(), visits);
+        candidate_ai.set_komi(komi);
+        best_ai.set_komi(komi);
+        let game_seed = seed.wrapping_add(2 * index as u64);
+        candidate_ai.set_exploration(game_seed, GATING_SAMPLING_MOVES);
+        best_ai.set_exploration(game_seed.wrapping_add(1), GATING_SAMPLING_MOVES);
+        let candidate_color = if index % 2 == 0 {GoStone::BLACK} else {GoStone::WHITE};
+
+        let mut game = GoGame::new();
+        while game.consecutive_passes() < 2 && game.moves().len() < GATING_MAX_MOVES {
+            let ai: &mut dyn GoAI = if game.current_turn() == candidate_color {&mut candidate_ai} else {&mut best_ai};
+            ai.set_game(game.clone());
+            let game_move = ai.best_move().filter(|&coordinates| game.is_legal(coordinates));
+            game.play(game_move).unwrap();
+        }
+
+        let winner = game_winner(&game, komi, seed.wrapping_add(index as u64));
+        if winner == candidate_color {
+            result.wins += 1;
+        } else if winner.is_none() {
+            result.draws += 1;
+        } else {
+            result.losses += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backward, forward, loss, zeroed, Optimizer, Trainer, TrainingSample};
+    use crate::go::board::symmetry::Symmetry;
+    use crate::go::nn::{Network, INPUT_PLANES, PASS_INDEX, POLICY_SIZE};
+    use rand_pcg::Pcg64Mcg;
+    use rand::{Rng, SeedableRng};
+
+    fn random_sample(random: &mut Pcg64Mcg) -> TrainingSample {
+        let features = (0..INPUT_PLANES * 81).map(|_| if random.gen_bool(0.3) {1.0} else {0.0}).collect();
+        let mut policy = vec![0.0; POLICY_SIZE];
+        policy[random.gen_range(0..POLICY_SIZE)] = 1.0;
+        TrainingSample::new(features, policy, if random.gen_bool(0.5) {1.0} else {0.0})
+    }
+
+    #[test]
+    fn test_gradients_match_finite_differences() {
+        let mut random = Pcg64Mcg::seed_from_u64(4);
+        let network = Network::new(3, 1, 4, 4);
+        let sample = random_sample(&mut random);
+
+        let mut gradients = zeroed(&network);
+        backward(&network, &forward(&network, &sample.features), &sample, &mut gradients);
+
+        let epsilon = 1e-2;
+        for layer in 0..network.layers().len() {
+            let last = network.layers()[layer].0.len() - 1;
+            for &index in &[0, last / 2, last] {
+                let mut plus = network.clone();
+                plus.layers_mut()[layer].0[index] += epsilon;
+                let mut minus = network.clone();
+                minus.layers_mut()[layer].0[index] -= epsilon;
+
+                let numeric = (loss(&forward(&plus, &sample.features), &sample) - loss(&forward(&minus, &sample.features), &sample)) / (2.0 * epsilon);
+                let analytic = gradients.layers()[layer].0[index];
+                assert!((numeric - analytic).abs() < 1e-2 + 0.05 * analytic.abs(), "layer {}: {} vs {}", layer, numeric, analytic);
+            }
+        }
+    }
+
+    #[test]
+    fn test_training_reduces_loss() {
+        let mut random = Pcg64Mcg::seed_from_u64(5);
+        let samples: Vec<_> = (0..4).map(|_| random_sample(&mut random)).collect();
+
+        for &optimizer in &[Optimizer::sgd(0.01), Optimizer::adam(0.003)] {
+            let mut trainer = Trainer::new(Network::new(4, 1, 8, 5), optimizer);
+            let before = trainer.loss(&samples);
+            for _ in 0..30 {
+                trainer.train_batch(&samples);
+            }
+            assert!(trainer.loss(&samples) < before);
+        }
+    }
+
+    #[test]
+    fn test_transformed_sample() {
+        let mut random = Pcg64Mcg::seed_from_u64(6);
+        let sample = random_sample(&mut random);
+        let transformed = sample.transformed(Symmetry::Rotate90);
+
+        assert_eq!(transformed.transformed(Symmetry::Rotate270), sample);
+        assert_eq!(transformed.policy()[PASS_INDEX], sample.policy()[PASS_INDEX]);
+        assert_eq!(transformed.policy().iter().sum::<f32>(), 1.0);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::board::symmetry::Symmetry;
+use crate::go::sgf::SgfNode;
+use crate::go::GoGame;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+pub const DEFAULT_ITERATIONS: usize = 20;
+
+/// The 8 neighbours of a point.
+const SQUARE_OFFSETS: [(i8, i8); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+/// The 12 points within manhattan distance 2 of a point.
+const DIAMOND_OFFSETS: [(i8, i8); 12] = [
+    (0, -2), (-1, -1), (0, -1), (1, -1), (-2, 0), (-1, 0), (1, 0), (2, 0), (-1, 1), (0, 1), (1, 1), (0, 2),
+];
+
+const EMPTY: u32 = 0;
+const OWN: u32 = 1;
+const OPPONENT: u32 = 2;
+const OFF_BOARD: u32 = 3;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum PatternShape {
+    Square,
+    Diamond,
+}
+
+/// A shape around a point, seen from the player to move and normalised over the 8 symmetries.
+/// The code has two bits per point: empty, own stone, opponent stone or off the board.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct Pattern {
+    shape: PatternShape,
+    code: u32,
+}
+
+/// The moves available in one position, each described by its patterns, and the index of the one that was played.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Competition {
+    pub candidates: Vec<Vec<Pattern>>,
+    pub winner: usize,
+}
+
+/// Bradley-Terry strengths of patterns. The strength of a move is the product of the strengths
+/// of its patterns, and a move is played with probability proportional to its strength.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PatternDatabase {
+    gammas: HashMap<Pattern, f32>,
+}
+
+impl PatternShape {
+    pub fn offsets(self) -> &'static [(i8, i8)] {
+        match self {
+            PatternShape::Square => &SQUARE_OFFSETS,
+            PatternShape::Diamond => &DIAMOND_OFFSETS,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PatternShape::Square => "square",
+            PatternShape::Diamond => "diamond",
+        }
+    }
+}
+
+impl Pattern {
+    pub fn new(board: &GoBoard, coordinates: GoCoordinates, to_move: GoStone, shape: PatternShape) -> Self {
+        let code = Symmetry::ALL.iter()
+            .map(|&symmetry| {
+                shape.offsets().iter().fold(0, |code, &(dx, dy)| {
+                    let (dx, dy) = symmetry.apply_offset(dx, dy);
+                    code * 4 + point_value(board, coordinates, dx, dy, to_move)
+                })
+            })
+            .min()
+            .unwrap();
+        Self {shape, code}
+    }
+
+    /// The patterns of both shapes.
+    pub fn all(board: &GoBoard, coordinates: GoCoordinates, to_move: GoStone) -> Vec<Self> {
+        vec![Self::new(board, coordinates, to_move, PatternShape::Square), Self::new(board, coordinates, to_move, PatternShape::Diamond)]
+    }
+
+    pub fn shape(&self) -> PatternShape {
+        self.shape
+    }
+
+    pub fn code(&self) -> u32 {
+        self.code
+    }
+}
+
+fn point_value(board: &GoBoard, coordinates: GoCoordinates, dx: i8, dy: i8, to_move: GoStone) -> u32 {
+    let x = coordinates.x() as i8 + dx;
+    let y = coordinates.y() as i8 + dy;
+    if x < 0 || y < 0 || x >= 9 || y >= 9 {
+        return OFF_BOARD;
+    }
+
+    let stone = board.get_stone(GoCoordinates::new(x as u8, y as u8));
+    if stone.is_none() {
+        EMPTY
+    } else if stone == to_move {
+        OWN
+    } else {
+        OPPONENT
+    }
+}
+
+impl Competition {
+    /// All legal moves of `game` with `played` as the winner, or `None` if `played` is a pass or not legal.
+    pub fn from_game(game: &GoGame, played: GoCoordinates) -> Option<Self> {
+        let mut candidates = Vec::new();
+        let mut winner = None;
+        for i in 0..9 * 9 {
+            let coordinates = GoCoordinates::from(i);
+            if game.is_legal(coordinates) {
+                if coordinates == played {
+                    winner = Some(candidates.len());
+                }
+                candidates.push(Pattern::all(game.current_board(), coordinates, game.current_turn()));
+            }
+        }
+        winner.map(|winner| Self {candidates, winner})
+    }
+}
+
+/// The competitions of the main lines of 9x9 games, skipping passes.
+pub fn competitions_from_sgf(roots: &[SgfNode]) -> Vec<Competition> {
+    let mut result = Vec::new();
+    for root in roots {
+        if root.property("SZ").is_some_and(|size| size != "9") {
+            continue;
+        }
+        let mut board = GoBoard::new();
+        if root.apply_setup(&mut board).is_err() {
+            continue;
+        }
+        let moves = match root.main_line_moves() {
+            Ok(moves) => moves,
+            Err(_) => continue,
+        };
+
+        let first_color = moves.first().map_or(GoStone::BLACK, |&(color, _)| color);
+        let mut game = GoGame::from_board(board, root.player_to_move().unwrap_or(first_color));
+        for (color, game_move) in moves {
+            if color != game.current_turn() {
+                break;
+            }
+            if let Some(coordinates) = game_move {
+                result.extend(Competition::from_game(&game, coordinates));
+            }
+            if game.play(game_move).is_err() {
+                break;
+            }
+        }
+    }
+    result
+}
+
+impl PatternDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fits the strengths to `competitions` with `iterations` rounds of Hunter's minorization-maximization.
+    /// Every pattern gets a prior of one win and one loss against a pattern of strength 1.
+    pub fn learn(competitions: &[Competition], iterations: usize) -> Self {
+        let mut wins: HashMap<Pattern, f32> = HashMap::new();
+        for competition in competitions {
+            for &pattern in competition.candidates.iter().flatten() {
+                wins.entry(pattern).or_insert(0.0);
+            }
+            for &pattern in &competition.candidates[competition.winner] {
+                *wins.get_mut(&pattern).unwrap() += 1.0;
+            }
+        }
+
+        let mut result = Self {gammas: wins.keys().map(|&pattern| (pattern, 1.0)).collect()};
+        let mut patterns: Vec<Pattern> = wins.keys().copied().collect();
+        patterns.sort();
+
+        for _ in 0..iterations {
+            for &shape in &[PatternShape::Square, PatternShape::Diamond] {
+                // Each shape is one group of the generalised Bradley-Terry model, updated together.
+                let mut denominators: HashMap<Pattern, f32> = HashMap::new();
+                for competition in competitions {
+                    let strengths: Vec<f32> = competition.candidates.iter().map(|candidate| result.strength(candidate)).collect();
+                    let total: f32 = strengths.iter().sum();
+                    for (candidate, strength) in competition.candidates.iter().zip(&strengths) {
+                        for pattern in candidate.iter().filter(|pattern| pattern.shape == shape) {
+                            *denominators.entry(*pattern).or_insert(0.0) += strength / result.gamma(pattern) / total;
+                        }
+                    }
+                }
+
+                for pattern in patterns.iter().filter(|pattern| pattern.shape == shape) {
+                    let gamma = result.gamma(pattern);
+                    let denominator = denominators.get(pattern).copied().unwrap_or(0.0) + 2.0 / (gamma + 1.0);
+                    result.gammas.insert(*pattern, (wins[pattern] + 1.0) / denominator);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn from_sgf(roots: &[SgfNode], iterations: usize) -> Self {
+        Self::learn(&competitions_from_sgf(roots), iterations)
+    }
+
+    pub fn len(&self) -> usize {
+        self.gammas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.gammas.is_empty()
+    }
+
+    /// The strength of `pattern`, 1 for patterns that were never seen.
+    pub fn gamma(&self, pattern: &Pattern) -> f32 {
+        self.gammas.get(pattern).copied().unwrap_or(1.0)
+    }
+
+    pub fn strength(&self, patterns: &[Pattern]) -> f32 {
+        patterns.iter().map(|pattern| self.gamma(pattern)).product()
+    }
+
+    pub fn score(&self, board: &GoBoard, coordinates: GoCoordinates, to_move: GoStone) -> f32 {
+        self.strength(&Pattern::all(board, coordinates, to_move))
+    }
+
+    /// The legal moves of `game` with the probability the model gives them, highest first.
+    pub fn move_probabilities(&self, game: &GoGame) -> Vec<(GoCoordinates, f32)> {
+        let mut result: Vec<(GoCoordinates, f32)> = (0..9 * 9).map(GoCoordinates::from)
+            .filter(|&coordinates| game.is_legal(coordinates))
+            .map(|coordinates| (coordinates, self.score(game.current_board(), coordinates, game.current_turn())))
+            .collect();
+
+        let total: f32 = result.iter().map(|&(_, strength)| strength).sum();
+        for entry in result.iter_mut() {
+            entry.1 /= total;
+        }
+        result.sort_by(|a, b| b.1.total_cmp(&a.1));
+        result
+    }
+
+    /// Writes one `shape code gamma` line per pattern.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut patterns: Vec<_> = self.gammas.iter().collect();
+        patterns.sort_by_key(|(pattern, _)| **pattern);
+        for (pattern, gamma) in patterns {
+            writeln!(writer, "{} {} {}", pattern.shape.name(), pattern.code, gamma)?;
+        }
+        writer.flush()
+    }
+
+    pub fn load<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut result = Self::new();
+        for line in reader.lines() {
+            let (pattern, gamma) = parse_gamma(&line?).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a pattern database"))?;
+            result.gammas.insert(pattern, gamma);
+        }
+        Ok(result)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::load(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Reads a `shape code gamma` line. The gamma has to be a positive number.
+fn parse_gamma(line: &str) -> Option<(Pattern, f32)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let shape = match parts[0] {
+        "square" => PatternShape::Square,
+        "diamond" => PatternShape::Diamond,
+        _ => return None,
+    };
+    let gamma: f32 = parts[2].parse().ok()?;
+    if !gamma.is_finite() || gamma <= 0.0 {
+        return None;
+    }
+    Some((Pattern {shape, code: parts[1].parse().ok()?}, gamma))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Competition, Pattern, PatternDatabase, PatternShape};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+    use crate::go::board::symmetry::Symmetry;
+
+    #[test]
+    fn test_patterns_are_normalised() {
+        let board = GoBoard::from_rows(&[
+            ".........",
+            "..XO.....",
+            "...X.....",
+        ]);
+        let point = GoCoordinates::new(2, 2);
+        for &shape in &[PatternShape::Square, PatternShape::Diamond] {
+            let pattern = Pattern::new(&board, point, GoStone::BLACK, shape);
+            for &symmetry in &Symmetry::ALL {
+                assert_eq!(Pattern::new(&symmetry.apply_board(&board), symmetry.apply(point), GoStone::BLACK, shape), pattern);
+            }
+            assert_ne!(Pattern::new(&board, point, GoStone::WHITE, shape), pattern);
+        }
+
+        let empty = GoBoard::new();
+        assert_ne!(Pattern::new(&empty, GoCoordinates::new(0, 4), GoStone::BLACK, PatternShape::Square),
+            Pattern::new(&empty, GoCoordinates::new(4, 4), GoStone::BLACK, PatternShape::Square));
+    }
+
+    #[test]
+    fn test_learning_prefers_played_patterns() {
+        let board = GoBoard::from_rows(&[
+            ".........",
+            "..XO.....",
+        ]);
+        let good = Pattern::all(&board, GoCoordinates::new(2, 2), GoStone::BLACK);
+        let bad = Pattern::all(&board, GoCoordinates::new(6, 6), GoStone::BLACK);
+        let mut competitions = Vec::new();
+        for i in 0..10 {
+            competitions.push(Competition {candidates: vec![good.clone(), bad.clone()], winner: if i < 8 {0} else {1}});
+        }
+
+        let database = PatternDatabase::learn(&competitions, 30);
+        let probability = database.strength(&good) / (database.strength(&good) + database.strength(&bad));
+        assert!(probability > 0.7 && probability < 0.85, "{}", probability);
+
+        let mut text = Vec::new();
+        database.save(&mut text).unwrap();
+        let loaded = PatternDatabase::load(&text[..]).unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert!((loaded.strength(&good) - database.strength(&good)).abs() < 1e-3);
+
+        assert!(PatternDatabase::load(&b"square 0 NaN\n"[..]).is_err());
+        assert!(PatternDatabase::load(&b"diamond 0 -1\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::board::analysis::eyes::is_true_eye;
+use crate::go::pattern::{Pattern, PatternDatabase, PatternShape};
+use crate::go::playout::{PlayoutPolicy, PlayoutState};
+use rand_pcg::Pcg64Mcg;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::sync::Arc;
+
+/// The heuristics of `HeavyPolicy`. The first four propose moves and are tried in this order,
+/// the last two filter the moves of the other rules and the random fallback.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum PlayoutRule {
+    /// Capture an opponent chain next to the last move that is in atari.
+    Capture,
+    /// Save an own chain next to the last move from atari, by extending or by capturing.
+    EscapeAtari,
+    /// Play the vital point of a three point eye space next to the last move.
+    Nakade,
+    /// Answer the last move with a good 3x3 shape around it.
+    PatternReply,
+    /// Skip moves that put an own chain of two or more stones into atari.
+    AvoidSelfAtari,
+    /// Skip moves that fill an own true eye.
+    ProtectEyes,
+}
+
+/// A playout policy built from `PlayoutRule`s. Each rule has a weight between 0 and 1: the
+/// probability that it is applied at a move. A weight of 0 turns the rule off.
+#[derive(Clone, Debug)]
+pub struct HeavyPolicy {
+    weights: [f32; 6],
+    patterns: Option<Arc<PatternDatabase>>,
+}
+
+impl PlayoutRule {
+    pub const ALL: [PlayoutRule; 6] = [
+        PlayoutRule::Capture, PlayoutRule::EscapeAtari, PlayoutRule::Nakade,
+        PlayoutRule::PatternReply, PlayoutRule::AvoidSelfAtari, PlayoutRule::ProtectEyes,
+    ];
+
+    fn index(self) -> usize {
+        PlayoutRule::ALL.iter().position(|&rule| rule == self).unwrap()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PlayoutRule::Capture => "capture",
+            PlayoutRule::EscapeAtari => "escape-atari",
+            PlayoutRule::Nakade => "nakade",
+            PlayoutRule::PatternReply => "pattern-reply",
+            PlayoutRule::AvoidSelfAtari => "avoid-self-atari",
+            PlayoutRule::ProtectEyes => "protect-eyes",
+        }
+    }
+}
+
+impl HeavyPolicy {
+    /// All rules on. Pattern replies need a pattern database to do anything.
+    pub fn new() -> Self {
+        Self {weights: [1.0; 6], patterns: None}
+    }
+
+    pub fn with_patterns(patterns: Arc<PatternDatabase>) -> Self {
+        Self {weights: [1.0; 6], patterns: Some(patterns)}
+    }
+
+    pub fn weight(&self, rule: PlayoutRule) -> f32 {
+        self.weights[rule.index()]
+    }
+
+    pub fn set_weight(&mut self, rule: PlayoutRule, weight: f32) {
+        self.weights[rule.index()] = weight.clamp(0.0, 1.0);
+    }
+
+    pub fn set_enabled(&mut self, rule: PlayoutRule, enabled: bool) {
+        self.set_weight(rule, if enabled {1.0} else {0.0});
+    }
+
+    fn applies(&self, rule: PlayoutRule, random: &mut Pcg64Mcg) -> bool {
+        let weight = self.weight(rule);
+        weight >= 1.0 || (weight > 0.0 && random.gen::<f32>() < weight)
+    }
+
+    fn proposals(&self, rule: PlayoutRule, state: &PlayoutState, last_move: GoCoordinates, random: &mut Pcg64Mcg) -> Vec<GoCoordinates> {
+        let board = state.board();
+        let color = state.current_turn();
+        match rule {
+            PlayoutRule::Capture => capture_moves(board, last_move, color),
+            PlayoutRule::EscapeAtari => escape_moves(board, last_move, color),
+            PlayoutRule::Nakade => nakade_moves(board, last_move),
+            PlayoutRule::PatternReply => match &self.patterns {
+                Some(patterns) => pattern_reply(patterns, board, last_move, color, random).into_iter().collect(),
+                None => Vec::new(),
+            },
+            PlayoutRule::AvoidSelfAtari | PlayoutRule::ProtectEyes => Vec::new(),
+        }
+    }
+
+    fn passes_filters(&self, state: &PlayoutState, coordinates: GoCoordinates, avoid_self_atari: bool, protect_eyes: bool) -> bool {
+        let color = state.current_turn();
+        if protect_eyes && is_true_eye(state.board(), coordinates, color) {
+            return false;
+        }
+        if avoid_self_atari && is_self_atari(state.board(), coordinates, color) {
+            return false;
+        }
+        state.is_legal(coordinates)
+    }
+}
+
+impl Default for HeavyPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlayoutPolicy for HeavyPolicy {
+    fn select_move(&mut self, state: &PlayoutState, random: &mut Pcg64Mcg) -> Option<GoCoordinates> {
+        let avoid_self_atari = self.applies(PlayoutRule::AvoidSelfAtari, random);
+        let protect_eyes = self.applies(PlayoutRule::ProtectEyes, random);
+
+        if let Some(last_move) = state.last_move() {
+            for &rule in &PlayoutRule::ALL[..4] {
+                if !self.applies(rule, random) {
+                    continue;
+                }
+
+                let mut moves = self.proposals(rule, state, last_move, random);
+                moves.shuffle(random);
+                // The vital point of a nakade is a self-atari by design.
+                let filtered = rule != PlayoutRule::Nakade;
+                if let Some(coordinates) = moves.into_iter().find(|&c| if filtered {self.passes_filters(state, c, avoid_self_atari, protect_eyes)} else {state.is_legal(c)}) {
+                    return Some(coordinates);
+                }
+            }
+        }
+
+        let mut candidates: Vec<_> = (0..9 * 9).map(GoCoordinates::from)
+            .filter(|&c| state.board().get_stone(c).is_none())
+            .collect();
+        candidates.shuffle(random);
+        candidates.into_iter().find(|&c| self.passes_filters(state, c, avoid_self_atari, protect_eyes))
+    }
+}
+
+/// The chains next to `last_move`, including its own.
+fn chains_near(board: &GoBoard, last_move: GoCoordinates, color: GoStone) -> Vec<Vec<GoCoordinates>> {
+    let mut result: Vec<Vec<GoCoordinates>> = Vec::new();
+    for point in std::iter::once(last_move).chain(last_move.neighbors()) {
+        if board.get_stone(point) == color && !result.iter().any(|chain| chain.contains(&point)) {
+            result.push(board.group(point));
+        }
+    }
+    result
+}
+
+fn capture_moves(board: &GoBoard, last_move: GoCoordinates, color: GoStone) -> Vec<GoCoordinates> {
+    chains_near(board, last_move, color.opponent_color()).iter()
+        .map(|chain| board.liberties(chain[0]))
+        .filter(|liberties| liberties.len() == 1)
+        .map(|liberties| liberties[0])
+        .collect()
+}
+
+fn escape_moves(board: &GoBoard, last_move: GoCoordinates, color: GoStone) -> Vec<GoCoordinates> {
+    let mut result = Vec::new();
+    for chain in chains_near(board, last_move, color) {
+        let liberties = board.liberties(chain[0]);
+        if liberties.len() != 1 {
+            continue;
+        }
+
+        if let Some((new_board, _)) = board.try_play(liberties[0], color) {
+            if new_board.liberties(liberties[0]).len() >= 2 {
+                result.push(liberties[0]);
+            }
+        }
+        for &stone in &chain {
+            for neighbor in stone.neighbors() {
+                if board.get_stone(neighbor) == color.opponent_color() {
+                    let opponent_liberties = board.liberties(neighbor);
+                    if opponent_liberties.len() == 1 && !result.contains(&opponent_liberties[0]) {
+                        result.push(opponent_liberties[0]);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// The middle of a straight three or the corner of a bent three. Eye spaces of three are the
+/// only ones with a single vital point for both sides.
+fn nakade_moves(board: &GoBoard, last_move: GoCoordinates) -> Vec<GoCoordinates> {
+    let mut result = Vec::new();
+    for neighbor in last_move.neighbors() {
+        if !board.get_stone(neighbor).is_none() {
+            continue;
+        }
+
+        let region = board.group(neighbor);
+        if region.len() != 3 || board.area_owner(neighbor).is_none() {
+            continue;
+        }
+        for &point in &region {
+            let inner_neighbors = point.neighbors().iter().filter(|n| region.contains(n)).count();
+            if inner_neighbors == 2 && !result.contains(&point) {
+                result.push(point);
+            }
+        }
+    }
+    result
+}
+
+/// Picks among the empty points around `last_move` whose 3x3 shape is stronger than average,
+/// with probability proportional to the strength.
+fn pattern_reply(patterns: &PatternDatabase, board: &GoBoard, last_move: GoCoordinates, color: GoStone, random: &mut Pcg64Mcg) -> Option<GoCoordinates> {
+    let candidates: Vec<(GoCoordinates, f32)> = last_move.neighbors().into_iter()
+        .chain(last_move.diagonals())
+        .filter(|&c| board.get_stone(c).is_none())
+        .map(|c| (c, patterns.gamma(&Pattern::new(board, c, color, PatternShape::Square))))
+        .filter(|&(_, gamma)| gamma > 1.0)
+        .collect();
+
+    let total: f32 = candidates.iter().map(|&(_, gamma)| gamma).sum();
+    let mut target = random.gen::<f32>() * total;
+    for &(coordinates, gamma) in &candidates {
+        target -= gamma;
+        if target <= 0.0 {
+            return Some(coordinates);
+        }
+    }
+    candidates.last().map(|&(coordinates, _)| coordinates)
+}
+
+fn is_self_atari(board: &GoBoard, coordinates: GoCoordinates, color: GoStone) -> bool {
+    match board.try_play(coordinates, color) {
+        Some((new_board, _)) => new_board.liberties(coordinates).len() == 1 && new_board.group(coordinates).len() >= 2,
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeavyPolicy, PlayoutRule};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+    use crate::go::playout::{PlayoutPolicy, PlayoutState};
+    use rand_pcg::Pcg64Mcg;
+    use rand::SeedableRng;
+
+    fn state_after(rows: &[&str], last_move: GoCoordinates, to_move: GoStone) -> PlayoutState {
+        let mut board = GoBoard::from_rows(rows);
+        let stone = board.get_stone(last_move);
+        board.set_stone(last_move, GoStone::NONE);
+        let mut state = PlayoutState::new(board, None, stone);
+        state.play(Some(last_move)).unwrap();
+        assert_eq!(state.current_turn(), to_move);
+        state
+    }
+
+    #[test]
+    fn test_rules_answer_the_last_move() {
+        let mut random = Pcg64Mcg::seed_from_u64(1);
+        let mut policy = HeavyPolicy::new();
+
+        // White has just put itself into atari at b1.
+        let capture = state_after(&["XO.......", ".X......."], GoCoordinates::new(1, 0), GoStone::BLACK);
+        assert_eq!(policy.select_move(&capture, &mut random), Some(GoCoordinates::new(2, 0)));
+
+        // White has just put the black stone on a1 into atari.
+        let escape = state_after(&["X........", "O........"], GoCoordinates::new(0, 1), GoStone::BLACK);
+        assert_eq!(policy.select_move(&escape, &mut random), Some(GoCoordinates::new(1, 0)));
+
+        // White has just made a straight three eye space on the edge.
+        let nakade = state_after(&[
+            "O...OX...",
+            "OOOOOX...",
+            "XXXXXX...",
+        ], GoCoordinates::new(0, 0), GoStone::BLACK);
+        assert_eq!(policy.select_move(&nakade, &mut random), Some(GoCoordinates::new(2, 0)));
+
+        policy.set_enabled(PlayoutRule::Nakade, false);
+        assert_ne!(policy.select_move(&nakade, &mut random), Some(GoCoordinates::new(2, 0)));
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut random = Pcg64Mcg::seed_from_u64(2);
+        let mut policy = HeavyPolicy::new();
+        policy.set_enabled(PlayoutRule::Capture, false);
+        policy.set_enabled(PlayoutRule::EscapeAtari, false);
+
+        // Every empty point is either a black eye or illegal for black.
+        let state = PlayoutState::new(GoBoard::from_rows(&[
+            ".X.X.XOOO",
+            "XXXXXXO.O",
+            "XXXXXXOO.",
+            "XXXXXXOOO",
+            "XXXXXXXXX",
+            ".X.XXXXXX",
+            "XXXXXXXXX",
+            "X.X.XXXXX",
+            "XXXXXXXXX",
+        ]), None, GoStone::BLACK);
+        assert_eq!(policy.select_move(&state, &mut random), None);
+
+        policy.set_enabled(PlayoutRule::ProtectEyes, false);
+        assert!(policy.select_move(&state, &mut random).is_some());
+    }
+}
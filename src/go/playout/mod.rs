@@ -0,0 +1,131 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::board::analysis::eyes::is_true_eye;
+use crate::go::{GoGame, IllegalMove};
+use rand_pcg::Pcg64Mcg;
+use rand::seq::SliceRandom;
+
+pub mod heavy;
+pub mod ownership;
+
+pub const MAX_PLAYOUT_MOVES: usize = 3 * 9 * 9;
+
+#[derive(Clone, Debug)]
+pub struct PlayoutState {
+    board: GoBoard,
+    previous_board: Option<GoBoard>,
+    current_turn: GoStone,
+    last_move: Option<GoCoordinates>,
+    passes: u8,
+    moves: usize,
+}
+
+/// Chooses the moves of a playout. Returning `None` passes.
+pub trait PlayoutPolicy: Clone + Send + 'static {
+    fn select_move(&mut self, state: &PlayoutState, random: &mut Pcg64Mcg) -> Option<GoCoordinates>;
+}
+
+/// Plays uniformly among the legal moves that do not fill an own eye.
+#[derive(Clone, Debug)]
+pub struct RandomPolicy;
+
+impl PlayoutState {
+    pub fn new(board: GoBoard, previous_board: Option<GoBoard>, current_turn: GoStone) -> Self {
+        assert!(!current_turn.is_none());
+        Self {board, previous_board, current_turn, last_move: None, passes: 0, moves: 0}
+    }
+
+    pub fn from_game(game: &GoGame) -> Self {
+        Self::new(game.current_board().clone(), game.previous_board().cloned(), game.current_turn())
+    }
+
+    pub fn board(&self) -> &GoBoard {
+        &self.board
+    }
+
+    pub fn current_turn(&self) -> GoStone {
+        self.current_turn
+    }
+
+    pub fn last_move(&self) -> Option<GoCoordinates> {
+        self.last_move
+    }
+
+    pub fn moves(&self) -> usize {
+        self.moves
+    }
+
+    /// Checks suicide and simple ko, which is the only repetition a playout looks at.
+    pub fn is_legal(&self, coordinates: GoCoordinates) -> bool {
+        match self.board.try_play(coordinates, self.current_turn) {
+            Some((new_board, _)) => self.previous_board.as_ref() != Some(&new_board),
+            None => false,
+        }
+    }
+
+    pub fn legal_moves(&self) -> Vec<GoCoordinates> {
+        (0..9 * 9).map(GoCoordinates::from).filter(|&c| self.is_legal(c)).collect()
+    }
+
+    pub fn play(&mut self, coordinates: Option<GoCoordinates>) -> Result<(), IllegalMove> {
+        match coordinates {
+            Some(coordinates) => {
+                if !self.board.get_stone(coordinates).is_none() {
+                    return Err(IllegalMove::Occupied);
+                }
+                let (new_board, _) = self.board.try_play(coordinates, self.current_turn).ok_or(IllegalMove::Suicide)?;
+                if self.previous_board.as_ref() == Some(&new_board) {
+                    return Err(IllegalMove::Superko);
+                }
+                self.previous_board = Some(std::mem::replace(&mut self.board, new_board));
+                self.passes = 0;
+            }
+            None => {
+                self.previous_board = Some(self.board.clone());
+                self.passes += 1;
+            }
+        }
+
+        self.last_move = coordinates;
+        self.current_turn = self.current_turn.opponent_color();
+        self.moves += 1;
+        Ok(())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.passes >= 2 || self.moves >= MAX_PLAYOUT_MOVES
+    }
+
+    pub fn area_score(&self) -> (u64, u64) {
+        let mut black = 0;
+        let mut white = 0;
+        for i in 0..9 * 9 {
+            match self.board.area_owner(i.into()) {
+                GoStone::BLACK => black += 1,
+                GoStone::WHITE => white += 1,
+                GoStone::NONE => {}
+            }
+        }
+        (black, white)
+    }
+}
+
+impl PlayoutPolicy for RandomPolicy {
+    fn select_move(&mut self, state: &PlayoutState, random: &mut Pcg64Mcg) -> Option<GoCoordinates> {
+        let color = state.current_turn();
+        let mut candidates: Vec<_> = (0..9 * 9).map(GoCoordinates::from)
+            .filter(|&c| state.board().get_stone(c).is_none() && !is_true_eye(state.board(), c, color))
+            .collect();
+        candidates.shuffle(random);
+        candidates.into_iter().find(|&c| state.is_legal(c))
+    }
+}
+
+pub fn run_playout<P: PlayoutPolicy>(mut state: PlayoutState, policy: &mut P, random: &mut Pcg64Mcg) -> PlayoutState {
+    while !state.is_finished() {
+        let coordinates = policy.select_move(&state, random);
+        if state.play(coordinates).is_err() {
+            state.play(None).unwrap();
+        }
+    }
+    state
+}
@@ -0,0 +1,207 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::board::analysis::benson::is_unconditionally_alive;
+use crate::go::board::analysis::seki::is_in_seki;
+use crate::go::playout::{PlayoutPolicy, PlayoutState, run_playout};
+use crate::go::GoGame;
+use rand_pcg::Pcg64Mcg;
+use rand::SeedableRng;
+use std::thread;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StoneStatus {
+    Alive,
+    Dead,
+    Seki,
+}
+
+/// How often each point belonged to black or white at the end of the playouts.
+#[derive(Clone, Debug)]
+pub struct OwnershipMap {
+    black: Vec<u32>,
+    white: Vec<u32>,
+    playouts: u32,
+}
+
+/// Runs `playouts` playouts from the current position of `game` on `threads` threads.
+/// Every playout is seeded from `seed` and its index, so the result does not
+/// depend on the number of threads.
+pub fn estimate_ownership<P: PlayoutPolicy>(game: &GoGame, policy: &P, playouts: usize, threads: usize, seed: u64) -> OwnershipMap {
+    let threads = threads.max(1);
+    let state = PlayoutState::from_game(game);
+
+    let handles: Vec<_> = (0..threads).map(|thread_index| {
+        let state = state.clone();
+        let mut policy = policy.clone();
+
+        thread::spawn(move || {
+            let mut result = OwnershipMap::new();
+            for playout in (thread_index..playouts).step_by(threads) {
+                let mut random = Pcg64Mcg::seed_from_u64(seed.wrapping_add(playout as u64));
+                let finished = run_playout(state.clone(), &mut policy, &mut random);
+                result.add_playout(finished.board());
+            }
+            result
+        })
+    }).collect();
+
+    let mut result = OwnershipMap::new();
+    for handle in handles {
+        result.merge(&handle.join().unwrap());
+    }
+    result
+}
+
+impl Default for OwnershipMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OwnershipMap {
+    pub fn new() -> Self {
+        Self {black: vec![0; 9 * 9], white: vec![0; 9 * 9], playouts: 0}
+    }
+
+    pub fn add_playout(&mut self, final_board: &GoBoard) {
+        for i in 0..9 * 9 {
+            match final_board.area_owner(i.into()) {
+                GoStone::BLACK => self.black[i] += 1,
+                GoStone::WHITE => self.white[i] += 1,
+                GoStone::NONE => {}
+            }
+        }
+        self.playouts += 1;
+    }
+
+    pub fn merge(&mut self, other: &OwnershipMap) {
+        for i in 0..9 * 9 {
+            self.black[i] += other.black[i];
+            self.white[i] += other.white[i];
+        }
+        self.playouts += other.playouts;
+    }
+
+    pub fn playouts(&self) -> u32 {
+        self.playouts
+    }
+
+    pub fn black(&self, coordinates: GoCoordinates) -> f32 {
+        self.probability(&self.black, coordinates)
+    }
+
+    pub fn white(&self, coordinates: GoCoordinates) -> f32 {
+        self.probability(&self.white, coordinates)
+    }
+
+    pub fn probability_of(&self, coordinates: GoCoordinates, color: GoStone) -> f32 {
+        match color {
+            GoStone::BLACK => self.black(coordinates),
+            GoStone::WHITE => self.white(coordinates),
+            GoStone::NONE => 1.0 - self.black(coordinates) - self.white(coordinates),
+        }
+    }
+
+    /// Ranges from -1 for certainly white to 1 for certainly black.
+    pub fn ownership(&self, coordinates: GoCoordinates) -> f32 {
+        self.black(coordinates) - self.white(coordinates)
+    }
+
+    pub fn owner(&self, coordinates: GoCoordinates, threshold: f32) -> GoStone {
+        if self.black(coordinates) >= threshold {
+            GoStone::BLACK
+        } else if self.white(coordinates) >= threshold {
+            GoStone::WHITE
+        } else {
+            GoStone::NONE
+        }
+    }
+
+    /// A group is dead if on average its points end up owned by the opponent more often than not,
+    /// unless Benson's algorithm proves it alive or it is in seki.
+    pub fn final_status(&self, board: &GoBoard, coordinates: GoCoordinates) -> StoneStatus {
+        let color = board.get_stone(coordinates);
+        assert!(!color.is_none());
+
+        if is_unconditionally_alive(board, coordinates) {
+            return StoneStatus::Alive;
+        }
+        if is_in_seki(board, coordinates) {
+            return StoneStatus::Seki;
+        }
+
+        let group = board.group(coordinates);
+        let opponent: f32 = group.iter().map(|&c| self.probability_of(c, color.opponent_color())).sum();
+        if opponent / group.len() as f32 > 0.5 {
+            StoneStatus::Dead
+        } else {
+            StoneStatus::Alive
+        }
+    }
+
+    /// All stones of `board` with the given status, like the GTP `final_status_list` command.
+    pub fn final_status_list(&self, board: &GoBoard, status: StoneStatus) -> Vec<GoCoordinates> {
+        (0..9 * 9).map(GoCoordinates::from)
+            .filter(|&c| !board.get_stone(c).is_none() && self.final_status(board, c) == status)
+            .collect()
+    }
+
+    pub fn dead_stones(&self, board: &GoBoard) -> Vec<GoCoordinates> {
+        self.final_status_list(board, StoneStatus::Dead)
+    }
+
+    fn probability(&self, counts: &[u32], coordinates: GoCoordinates) -> f32 {
+        if self.playouts == 0 {
+            0.0
+        } else {
+            counts[usize::from(coordinates)] as f32 / self.playouts as f32
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_ownership, StoneStatus};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+    use crate::go::playout::RandomPolicy;
+    use crate::go::GoGame;
+
+    fn game() -> GoGame {
+        let mut board = GoBoard::new();
+        for y in 0..9 {
+            board.set_stone(GoCoordinates::new(3, y), GoStone::BLACK);
+            board.set_stone(GoCoordinates::new(4, y), GoStone::WHITE);
+        }
+        board.set_stone(GoCoordinates::new(1, 1), GoStone::BLACK);
+        board.set_stone(GoCoordinates::new(1, 7), GoStone::BLACK);
+        board.set_stone(GoCoordinates::new(7, 1), GoStone::WHITE);
+        board.set_stone(GoCoordinates::new(7, 7), GoStone::WHITE);
+        board.set_stone(GoCoordinates::new(1, 4), GoStone::WHITE);
+        GoGame::from_board(board, GoStone::BLACK)
+    }
+
+    #[test]
+    fn test_ownership_is_seeded() {
+        let game = game();
+        let first = estimate_ownership(&game, &RandomPolicy, 16, 1, 42);
+        let second = estimate_ownership(&game, &RandomPolicy, 16, 3, 42);
+
+        assert_eq!(first.playouts(), 16);
+        for i in 0..9 * 9 {
+            assert_eq!(first.black(i.into()), second.black(i.into()));
+            assert_eq!(first.white(i.into()), second.white(i.into()));
+        }
+    }
+
+    #[test]
+    fn test_dead_stone_estimation() {
+        let game = game();
+        let board = game.current_board();
+        let ownership = estimate_ownership(&game, &RandomPolicy, 64, 2, 7);
+
+        assert!(ownership.black(GoCoordinates::new(0, 0)) > 0.5);
+        assert!(ownership.white(GoCoordinates::new(8, 8)) > 0.5);
+        assert_eq!(ownership.final_status(board, GoCoordinates::new(1, 4)), StoneStatus::Dead);
+        assert_eq!(ownership.final_status(board, GoCoordinates::new(3, 4)), StoneStatus::Alive);
+        assert_eq!(ownership.dead_stones(board), vec![GoCoordinates::new(1, 4)]);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+use crate::go::board::analysis::seki::find_seki;
+
+pub const DEFAULT_KOMI: f32 = 7.0;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Ruleset {
+    /// Territory scoring: territory plus prisoners. Points in seki are not territory.
+    Japanese,
+    /// Area scoring: territory plus stones on the board.
+    Chinese,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameScore {
+    ruleset: Ruleset,
+    komi: f32,
+    black_territory: u64,
+    white_territory: u64,
+    black_stones: u64,
+    white_stones: u64,
+    black_prisoners: u64,
+    white_prisoners: u64,
+}
+
+/// Scores `board` after removing `dead_stones`, which count as prisoners of the opponent.
+/// The captures are the number of stones each color took during the game.
+pub fn score(board: &GoBoard, dead_stones: &GoBoardMask, black_captures: u64, white_captures: u64, komi: f32, ruleset: Ruleset) -> GameScore {
+    let (board, black_dead, white_dead) = remove_dead_stones(board, dead_stones);
+    let mut result = GameScore {
+        ruleset, komi, black_territory: 0, white_territory: 0,
+        black_stones: board.count(GoStone::BLACK).into(), white_stones: board.count(GoStone::WHITE).into(),
+        black_prisoners: black_captures + white_dead, white_prisoners: white_captures + black_dead,
+    };
+
+    for owner in territory_owners(&board, ruleset) {
+        match owner {
+            GoStone::BLACK => result.black_territory += 1,
+            GoStone::WHITE => result.white_territory += 1,
+            GoStone::NONE => {}
+        }
+    }
+
+    result
+}
+
+/// For every point, the color whose territory it is after removing `dead_stones`, or `GoStone::NONE`.
+pub fn territory(board: &GoBoard, dead_stones: &GoBoardMask, ruleset: Ruleset) -> Vec<GoStone> {
+    territory_owners(&remove_dead_stones(board, dead_stones).0, ruleset)
+}
+
+/// The board without `dead_stones`, and the number of black and white stones removed.
+fn remove_dead_stones(board: &GoBoard, dead_stones: &GoBoardMask) -> (GoBoard, u64, u64) {
+    let mut board = board.clone();
+    let mut black_dead = 0;
+    let mut white_dead = 0;
+
+    for i in 0..9 * 9 {
+        let coordinates = GoCoordinates::from(i);
+        if dead_stones.get(coordinates) {
+            match board.get_stone(coordinates) {
+                GoStone::BLACK => black_dead += 1,
+                GoStone::WHITE => white_dead += 1,
+                GoStone::NONE => continue,
+            }
+            board.set_stone(coordinates, GoStone::NONE);
+        }
+    }
+
+    (board, black_dead, white_dead)
+}
+
+fn territory_owners(board: &GoBoard, ruleset: Ruleset) -> Vec<GoStone> {
+    let seki = find_seki(board);
+
+    (0..9 * 9).map(GoCoordinates::from).map(|coordinates| {
+        if !board.get_stone(coordinates).is_none() || seki.shared_liberties().get(coordinates)
+            || (ruleset == Ruleset::Japanese && seki.eyes().get(coordinates)) {
+            GoStone::NONE
+        } else {
+            board.area_owner(coordinates)
+        }
+    }).collect()
+}
+
+impl GameScore {
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    pub fn komi(&self) -> f32 {
+        self.komi
+    }
+
+    pub fn territory(&self, color: GoStone) -> u64 {
+        if color == GoStone::BLACK {self.black_territory} else {self.white_territory}
+    }
+
+    pub fn stones(&self, color: GoStone) -> u64 {
+        if color == GoStone::BLACK {self.black_stones} else {self.white_stones}
+    }
+
+    pub fn prisoners(&self, color: GoStone) -> u64 {
+        if color == GoStone::BLACK {self.black_prisoners} else {self.white_prisoners}
+    }
+
+    /// Territory score: territory plus prisoners.
+    pub fn territory_score(&self, color: GoStone) -> f32 {
+        let komi = if color == GoStone::WHITE {self.komi} else {0.0};
+        (self.territory(color) + self.prisoners(color)) as f32 + komi
+    }
+
+    /// Area score: territory plus stones.
+    pub fn area_score(&self, color: GoStone) -> f32 {
+        let komi = if color == GoStone::WHITE {self.komi} else {0.0};
+        (self.territory(color) + self.stones(color)) as f32 + komi
+    }
+
+    /// The score of `color` under the ruleset this score was counted with.
+    pub fn total(&self, color: GoStone) -> f32 {
+        match self.ruleset {
+            Ruleset::Japanese => self.territory_score(color),
+            Ruleset::Chinese => self.area_score(color),
+        }
+    }
+
+    /// Positive if black wins.
+    pub fn margin(&self) -> f32 {
+        self.total(GoStone::BLACK) - self.total(GoStone::WHITE)
+    }
+
+    /// `GoStone::NONE` for a draw.
+    pub fn winner(&self) -> GoStone {
+        let margin = self.margin();
+        if margin > 0.0 {
+            GoStone::BLACK
+        } else if margin < 0.0 {
+            GoStone::WHITE
+        } else {
+            GoStone::NONE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{score, territory, Ruleset};
+    use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_seki_is_not_territory() {
+        let board = GoBoard::from_rows(&[
+            "O.XXX.OX.",
+            "OOOOOOOX.",
+            "XXXXXXXX.",
+        ]);
+
+        let score = score(&board, &GoBoardMask::new(), 0, 0, 0.0, Ruleset::Japanese);
+        assert_eq!(score.territory(GoStone::WHITE), 0);
+        assert_eq!(score.territory(GoStone::BLACK), 9 * 9 - 22 - 2);
+    }
+
+    #[test]
+    fn test_dead_stones_are_prisoners() {
+        let mut board = GoBoard::new();
+        for y in 0..9 {
+            board.set_stone(GoCoordinates::new(3, y), GoStone::BLACK);
+            board.set_stone(GoCoordinates::new(4, y), GoStone::WHITE);
+        }
+        board.set_stone(GoCoordinates::new(1, 1), GoStone::WHITE);
+        let mut dead_stones = GoBoardMask::new();
+        dead_stones.set(GoCoordinates::new(1, 1), true);
+
+        let japanese = score(&board, &dead_stones, 2, 1, 6.5, Ruleset::Japanese);
+        assert_eq!(japanese.territory(GoStone::BLACK), 27);
+        assert_eq!(japanese.territory(GoStone::WHITE), 36);
+        assert_eq!(japanese.prisoners(GoStone::BLACK), 3);
+        assert_eq!(japanese.total(GoStone::BLACK), 30.0);
+        assert_eq!(japanese.total(GoStone::WHITE), 43.5);
+        assert_eq!(japanese.winner(), GoStone::WHITE);
+
+        let chinese = score(&board, &dead_stones, 2, 1, 6.5, Ruleset::Chinese);
+        assert_eq!(chinese.total(GoStone::BLACK), 36.0);
+        assert_eq!(chinese.margin(), -15.5);
+
+        let owners = territory(&board, &dead_stones, Ruleset::Japanese);
+        assert_eq!(owners[usize::from(GoCoordinates::new(1, 1))], GoStone::BLACK);
+        assert_eq!(owners[usize::from(GoCoordinates::new(3, 1))], GoStone::NONE);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+use crate::go::board::tritvector::Trit;
+use crate::go::ai::GoAI;
+use crate::go::playout::RandomPolicy;
+use crate::go::playout::ownership::estimate_ownership;
+use crate::go::scoring::{score, Ruleset};
+use crate::go::sgf::format_coordinates;
+use crate::go::GoGame;
+use std::io::{self, Read, Write};
+
+/// The number of positions stored with each record, the current one first.
+pub const HISTORY_LENGTH: usize = 3;
+pub const OWNERSHIP_PLAYOUTS: usize = 200;
+
+const MAGIC: &[u8; 4] = b"GOSP";
+const VERSION: u8 = 1;
+const PASS: u8 = 9 * 9;
+const BOARD_BYTES: usize = usize::div_ceil(9 * 9, 5);
+
+/// One position of a self-play game with the move that was played there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfPlayRecord {
+    history: Vec<GoBoard>,
+    to_move: GoStone,
+    played: Option<GoCoordinates>,
+    visits: Vec<(Option<GoCoordinates>, u32)>,
+    winner: GoStone,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DatasetFormat {
+    /// `GOSP` header followed by fixed layout records, boards packed five trits per byte.
+    Binary,
+    /// One JSON object per position.
+    Jsonl,
+}
+
+/// Lets `ai` play a game against itself, starting from an empty board. Illegal moves count as passes.
+/// The game ends after two passes or `max_moves` moves and is scored with area scoring,
+/// taking dead stones from playouts seeded with `seed`.
+pub fn play_game(ai: &mut dyn GoAI, komi: f32, max_moves: usize, seed: u64) -> Vec<SelfPlayRecord> {
+    let mut game = GoGame::new();
+    let mut result = Vec::new();
+
+    while game.consecutive_passes() < 2 && game.moves().len() < max_moves {
+        ai.set_game(game.clone());
+        let mut played = ai.best_move();
+        if played.is_some_and(|coordinates| !game.is_legal(coordinates)) {
+            played = None;
+        }
+
+        let history = game.history().iter().rev().take(HISTORY_LENGTH).cloned().collect();
+        result.push(SelfPlayRecord {history, to_move: game.current_turn(), played, visits: ai.visit_counts(), winner: GoStone::NONE});
+        game.play(played).unwrap();
+    }
+
+    let winner = game_winner(&game, komi, seed);
+    for record in &mut result {
+        record.winner = winner;
+    }
+    result
+}
+
+/// Scores a finished game with area scoring, taking dead stones from playouts seeded with `seed`.
+pub fn game_winner(game: &GoGame, komi: f32, seed: u64) -> GoStone {
+    let ownership = estimate_ownership(game, &RandomPolicy, OWNERSHIP_PLAYOUTS, 1, seed);
+    let mut dead_stones = GoBoardMask::new();
+    for coordinates in ownership.dead_stones(game.current_board()) {
+        dead_stones.set(coordinates, true);
+    }
+    score(game.current_board(), &dead_stones, game.black_captures(), game.white_captures(), komi, Ruleset::Chinese).winner()
+}
+
+impl SelfPlayRecord {
+    pub fn new(history: Vec<GoBoard>, to_move: GoStone, played: Option<GoCoordinates>, visits: Vec<(Option<GoCoordinates>, u32)>, winner: GoStone) -> Self {
+        assert!(!history.is_empty() && history.len() <= HISTORY_LENGTH);
+        Self {history, to_move, played, visits, winner}
+    }
+
+    pub fn board(&self) -> &GoBoard {
+        &self.history[0]
+    }
+
+    /// The current position followed by the positions before it.
+    pub fn history(&self) -> &[GoBoard] {
+        &self.history
+    }
+
+    pub fn to_move(&self) -> GoStone {
+        self.to_move
+    }
+
+    pub fn played(&self) -> Option<GoCoordinates> {
+        self.played
+    }
+
+    pub fn visits(&self) -> &[(Option<GoCoordinates>, u32)] {
+        &self.visits
+    }
+
+    /// `GoStone::NONE` for a draw.
+    pub fn winner(&self) -> GoStone {
+        self.winner
+    }
+}
+
+pub struct DatasetWriter<W: Write> {
+    writer: W,
+    format: DatasetFormat,
+}
+
+impl<W: Write> DatasetWriter<W> {
+    pub fn new(mut writer: W, format: DatasetFormat) -> io::Result<Self> {
+        if format == DatasetFormat::Binary {
+            writer.write_all(MAGIC)?;
+            writer.write_all(&[VERSION])?;
+        }
+        Ok(Self {writer, format})
+    }
+
+    pub fn write(&mut self, record: &SelfPlayRecord) -> io::Result<()> {
+        match self.format {
+            DatasetFormat::Binary => self.writer.write_all(&encode_record(record)),
+            DatasetFormat::Jsonl => writeln!(self.writer, "{}", to_json(record)),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads every record of a binary dataset.
+pub fn read_binary<R: Read>(mut reader: R) -> io::Result<Vec<SelfPlayRecord>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    decode_records(&bytes).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "not a binary self-play dataset"))
+}
+
+fn decode_records(bytes: &[u8]) -> Result<Vec<SelfPlayRecord>, ()> {
+    if bytes.len() < 5 || &bytes[0..4] != MAGIC || bytes[4] != VERSION {
+        return Err(());
+    }
+
+    let mut bytes = bytes[5..].iter().copied();
+    let mut result = Vec::new();
+    while let Some(count) = bytes.next() {
+        let mut history = Vec::new();
+        for _ in 0..count {
+            let packed: Vec<u8> = bytes.by_ref().take(BOARD_BYTES).collect();
+            if packed.len() != BOARD_BYTES {
+                return Err(());
+            }
+            history.push(unpack_board(&packed));
+        }
+
+        let to_move = decode_stone(bytes.next().ok_or(())?)?;
+        let played = decode_move(bytes.next().ok_or(())?)?;
+        let mut visits = Vec::new();
+        for _ in 0..bytes.next().ok_or(())? {
+            let game_move = decode_move(bytes.next().ok_or(())?)?;
+            let count: Vec<u8> = bytes.by_ref().take(4).collect();
+            if count.len() != 4 {
+                return Err(());
+            }
+            visits.push((game_move, u32::from_le_bytes([count[0], count[1], count[2], count[3]])));
+        }
+        let winner = decode_stone(bytes.next().ok_or(())?)?;
+
+        if history.is_empty() || history.len() > HISTORY_LENGTH {
+            return Err(());
+        }
+        result.push(SelfPlayRecord {history, to_move, played, visits, winner});
+    }
+    Ok(result)
+}
+
+fn encode_record(record: &SelfPlayRecord) -> Vec<u8> {
+    let mut result = vec![record.history.len() as u8];
+    for board in &record.history {
+        result.extend(pack_board(board));
+    }
+    result.push(trit_value(record.to_move));
+    result.push(encode_move(record.played));
+    result.push(record.visits.len().min(u8::MAX as usize) as u8);
+    for &(game_move, visits) in record.visits.iter().take(u8::MAX as usize) {
+        result.push(encode_move(game_move));
+        result.extend(&visits.to_le_bytes());
+    }
+    result.push(trit_value(record.winner));
+    result
+}
+
+/// Packs the trits of `board` in base 3, five per byte.
+fn pack_board(board: &GoBoard) -> Vec<u8> {
+    let mut result = vec![0u8; BOARD_BYTES];
+    for i in (0..9 * 9).rev() {
+        result[i / 5] = result[i / 5] * 3 + trit_value(board.get_stone(GoCoordinates::from(i)));
+    }
+    result
+}
+
+fn unpack_board(packed: &[u8]) -> GoBoard {
+    let mut board = GoBoard::new();
+    for i in 0..9 * 9 {
+        let digit = packed[i / 5] / 3u8.pow((i % 5) as u32) % 3;
+        board.set_stone(GoCoordinates::from(i), decode_stone(digit).unwrap());
+    }
+    board
+}
+
+fn trit_value(stone: GoStone) -> u8 {
+    match Trit::from(stone) {
+        Trit::ZERO => 0,
+        Trit::ONE => 1,
+        Trit::TWO => 2,
+    }
+}
+
+fn decode_stone(value: u8) -> Result<GoStone, ()> {
+    match value {
+        0 => Ok(GoStone::from(Trit::ZERO)),
+        1 => Ok(GoStone::from(Trit::ONE)),
+        2 => Ok(GoStone::from(Trit::TWO)),
+        _ => Err(()),
+    }
+}
+
+fn encode_move(game_move: Option<GoCoordinates>) -> u8 {
+    game_move.map_or(PASS, |coordinates| usize::from(coordinates) as u8)
+}
+
+fn decode_move(value: u8) -> Result<Option<GoCoordinates>, ()> {
+    match value {
+        PASS => Ok(None),
+        value if value < PASS => Ok(Some(GoCoordinates::from(value as usize))),
+        _ => Err(()),
+    }
+}
+
+fn format_move(game_move: Option<GoCoordinates>) -> String {
+    match game_move {
+        Some(_) => format_coordinates(game_move),
+        None => "pass".to_string(),
+    }
+}
+
+fn format_color(stone: GoStone) -> &'static str {
+    match stone {
+        GoStone::BLACK => "B",
+        GoStone::WHITE => "W",
+        GoStone::NONE => "",
+    }
+}
+
+/// Boards are strings of 81 digits in row order: 0 empty, 1 black, 2 white.
+fn to_json(record: &SelfPlayRecord) -> String {
+    let history: Vec<String> = record.history.iter()
+        .map(|board| {
+            let trits: String = (0..9 * 9).map(|i| (b'0' + trit_value(board.get_stone(GoCoordinates::from(i)))) as char).collect();
+            format!("\"{}\"", trits)
+        })
+        .collect();
+    let visits: Vec<String> = record.visits.iter()
+        .map(|&(game_move, visits)| format!("[\"{}\",{}]", format_move(game_move), visits))
+        .collect();
+
+    format!("{{\"history\":[{}],\"to_move\":\"{}\",\"move\":\"{}\",\"visits\":[{}],\"winner\":\"{}\"}}",
+        history.join(","), format_color(record.to_move), format_move(record.played), visits.join(","), format_color(record.winner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{play_game, read_binary, DatasetFormat, DatasetWriter, SelfPlayRecord};
+    use crate::go::ai::mcts_ai::MctsAI;
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+
+    #[test]
+    fn test_binary_round_trip() {
+        let board = GoBoard::from_rows(&[
+            "XO.......",
+            "........O",
+        ]);
+        let record = SelfPlayRecord::new(vec![board, GoBoard::new()], GoStone::WHITE, None,
+            vec![(Some(GoCoordinates::new(8, 8)), 70000), (None, 3)], GoStone::BLACK);
+
+        let mut writer = DatasetWriter::new(Vec::new(), DatasetFormat::Binary).unwrap();
+        writer.write(&record).unwrap();
+        writer.write(&record).unwrap();
+        let bytes = writer.into_inner();
+
+        assert_eq!(read_binary(&bytes[..]).unwrap(), vec![record.clone(), record]);
+    }
+
+    #[test]
+    fn test_self_play_is_reproducible() {
+        let first = play_game(&mut MctsAI::with_seed(3, 20), 7.0, 10, 3);
+        let second = play_game(&mut MctsAI::with_seed(3, 20), 7.0, 10, 3);
+
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, second);
+        assert!(first.iter().all(|record| record.winner() == first[0].winner()));
+        assert_eq!(first[1].history().len(), 2);
+    }
+}
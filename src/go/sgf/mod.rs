@@ -0,0 +1,403 @@
+use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+use crate::go::GoGame;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A node of an SGF game tree. Sequences are stored as chains of single children,
+/// variations as several children.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SgfNode {
+    properties: Vec<(String, Vec<String>)>,
+    children: Vec<SgfNode>,
+}
+
+/// SGF text or a property value that can not be read.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SgfError;
+
+impl fmt::Display for SgfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid SGF")
+    }
+}
+
+impl Default for SgfNode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SgfNode {
+    pub fn new() -> Self {
+        Self {properties: Vec::new(), children: Vec::new()}
+    }
+
+    pub fn property(&self, identifier: &str) -> Option<&str> {
+        self.property_values(identifier).first().map(|value| value.as_str())
+    }
+
+    pub fn property_values(&self, identifier: &str) -> &[String] {
+        self.properties.iter()
+            .find(|(id, _)| id == identifier)
+            .map_or(&[], |(_, values)| values.as_slice())
+    }
+
+    pub fn properties(&self) -> &[(String, Vec<String>)] {
+        &self.properties
+    }
+
+    pub fn set_property(&mut self, identifier: &str, value: &str) {
+        self.properties.retain(|(id, _)| id != identifier);
+        self.properties.push((identifier.to_string(), vec![value.to_string()]));
+    }
+
+    pub fn add_property_value(&mut self, identifier: &str, value: &str) {
+        match self.properties.iter_mut().find(|(id, _)| id == identifier) {
+            Some((_, values)) => values.push(value.to_string()),
+            None => self.properties.push((identifier.to_string(), vec![value.to_string()])),
+        }
+    }
+
+    pub fn children(&self) -> &[SgfNode] {
+        &self.children
+    }
+
+    pub fn children_mut(&mut self) -> &mut Vec<SgfNode> {
+        &mut self.children
+    }
+
+    pub fn add_child(&mut self, child: SgfNode) {
+        self.children.push(child);
+    }
+
+    /// The move stored in this node, if it has a `B` or `W` property.
+    pub fn get_move(&self) -> Result<Option<(GoStone, Option<GoCoordinates>)>, SgfError> {
+        if let Some(value) = self.property("B") {
+            Ok(Some((GoStone::BLACK, parse_coordinates(value)?)))
+        } else if let Some(value) = self.property("W") {
+            Ok(Some((GoStone::WHITE, parse_coordinates(value)?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The nodes from this one following the first child each time.
+    pub fn main_line(&self) -> Vec<&SgfNode> {
+        let mut result = vec![self];
+        let mut node = self;
+        while let Some(child) = node.children.first() {
+            result.push(child);
+            node = child;
+        }
+        result
+    }
+
+    /// The moves of the main line, where `None` is a pass.
+    pub fn main_line_moves(&self) -> Result<Vec<(GoStone, Option<GoCoordinates>)>, SgfError> {
+        let mut result = Vec::new();
+        for node in self.main_line() {
+            if let Some(game_move) = node.get_move()? {
+                result.push(game_move);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Applies the `AB`, `AW` and `AE` setup properties of this node to `board`.
+    pub fn apply_setup(&self, board: &mut GoBoard) -> Result<(), SgfError> {
+        for &(identifier, stone) in &[("AE", GoStone::NONE), ("AB", GoStone::BLACK), ("AW", GoStone::WHITE)] {
+            for value in self.property_values(identifier) {
+                for coordinates in parse_point_list(value)? {
+                    board.set_stone(coordinates, stone);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The winner from the `RE` property, `GoStone::NONE` for a draw and `None` if unknown.
+    pub fn winner(&self) -> Option<GoStone> {
+        match self.property("RE") {
+            Some(result) if result.starts_with("B+") => Some(GoStone::BLACK),
+            Some(result) if result.starts_with("W+") => Some(GoStone::WHITE),
+            Some("0") | Some("Draw") => Some(GoStone::NONE),
+            _ => None,
+        }
+    }
+
+    /// The side to move from the `PL` property.
+    pub fn player_to_move(&self) -> Option<GoStone> {
+        match self.property("PL") {
+            Some("B") => Some(GoStone::BLACK),
+            Some("W") => Some(GoStone::WHITE),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the first game tree of an SGF collection.
+pub fn parse(text: &str) -> Result<SgfNode, SgfError> {
+    parse_collection(text)?.into_iter().next().ok_or(SgfError)
+}
+
+pub fn parse_collection(text: &str) -> Result<Vec<SgfNode>, SgfError> {
+    let mut chars = text.chars().peekable();
+    let mut result = Vec::new();
+
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.peek() {
+            Some('(') => result.push(parse_game_tree(&mut chars)?),
+            None => break,
+            Some(_) => return Err(SgfError),
+        }
+    }
+
+    Ok(result)
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_game_tree(chars: &mut Peekable<Chars>) -> Result<SgfNode, SgfError> {
+    if chars.next() != Some('(') {
+        return Err(SgfError);
+    }
+
+    let mut sequence = Vec::new();
+    let mut variations = Vec::new();
+    loop {
+        skip_whitespace(chars);
+        match chars.peek() {
+            Some(';') if variations.is_empty() => {
+                chars.next();
+                sequence.push(parse_node(chars)?);
+            }
+            Some('(') => variations.push(parse_game_tree(chars)?),
+            Some(')') => {
+                chars.next();
+                break;
+            }
+            _ => return Err(SgfError),
+        }
+    }
+
+    let mut node = sequence.pop().ok_or(SgfError)?;
+    node.children = variations;
+    while let Some(mut parent) = sequence.pop() {
+        parent.children.push(node);
+        node = parent;
+    }
+    Ok(node)
+}
+
+fn parse_node(chars: &mut Peekable<Chars>) -> Result<SgfNode, SgfError> {
+    let mut node = SgfNode::new();
+
+    loop {
+        skip_whitespace(chars);
+        let mut identifier = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_alphabetic() {
+                identifier.push(c);
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if identifier.is_empty() {
+            return Ok(node);
+        }
+
+        skip_whitespace(chars);
+        if chars.peek() != Some(&'[') {
+            return Err(SgfError);
+        }
+        while chars.peek() == Some(&'[') {
+            chars.next();
+            let value = parse_value(chars)?;
+            node.add_property_value(&identifier, &value);
+            skip_whitespace(chars);
+        }
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<String, SgfError> {
+    let mut result = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => result.push(chars.next().ok_or(SgfError)?),
+            Some(']') => return Ok(result),
+            Some(c) => result.push(c),
+            None => return Err(SgfError),
+        }
+    }
+}
+
+/// The game record of `game` with its starting position, moves and result if it is over.
+pub fn from_game(game: &GoGame, komi: f32) -> SgfNode {
+    let mut root = SgfNode::new();
+    root.set_property("GM", "1");
+    root.set_property("FF", "4");
+    root.set_property("SZ", "9");
+    root.set_property("KM", &komi.to_string());
+    if let Some(result) = game.result() {
+        root.set_property("RE", &result.to_string());
+    }
+
+    let start = &game.history()[0];
+    for (identifier, color) in &[("AB", GoStone::BLACK), ("AW", GoStone::WHITE)] {
+        for coordinates in (0..9 * 9).map(GoCoordinates::from).filter(|&c| start.get_stone(c) == *color) {
+            root.add_property_value(identifier, &format_coordinates(Some(coordinates)));
+        }
+    }
+
+    let mut color = if game.moves().len().is_multiple_of(2) {game.current_turn()} else {game.current_turn().opponent_color()};
+    if color == GoStone::WHITE {
+        root.set_property("PL", "W");
+    }
+
+    let mut nodes = Vec::new();
+    for &game_move in game.moves() {
+        let mut node = SgfNode::new();
+        node.set_property(if color == GoStone::BLACK {"B"} else {"W"}, &format_coordinates(game_move));
+        nodes.push(node);
+        color = color.opponent_color();
+    }
+    if let Some(mut node) = nodes.pop() {
+        while let Some(mut parent) = nodes.pop() {
+            parent.add_child(node);
+            node = parent;
+        }
+        root.add_child(node);
+    }
+    root
+}
+
+/// Writes `root` as a single game tree.
+pub fn to_sgf(root: &SgfNode) -> String {
+    let mut result = String::new();
+    write_game_tree(root, &mut result);
+    result
+}
+
+fn write_game_tree(node: &SgfNode, result: &mut String) {
+    result.push('(');
+    let mut node = node;
+    loop {
+        write_node(node, result);
+        if node.children.len() == 1 {
+            node = &node.children[0];
+        } else {
+            break;
+        }
+    }
+    for child in &node.children {
+        write_game_tree(child, result);
+    }
+    result.push(')');
+}
+
+fn write_node(node: &SgfNode, result: &mut String) {
+    result.push(';');
+    for (identifier, values) in &node.properties {
+        result.push_str(identifier);
+        for value in values {
+            result.push('[');
+            for c in value.chars() {
+                if c == ']' || c == '\\' {
+                    result.push('\\');
+                }
+                result.push(c);
+            }
+            result.push(']');
+        }
+    }
+}
+
+/// Parses a point like `cd`. The empty value and `tt` are passes.
+pub fn parse_coordinates(value: &str) -> Result<Option<GoCoordinates>, SgfError> {
+    if value.is_empty() || value == "tt" {
+        return Ok(None);
+    }
+
+    let bytes = value.as_bytes();
+    if bytes.len() != 2 || bytes[0] < b'a' || bytes[0] >= b'a' + 9 || bytes[1] < b'a' || bytes[1] >= b'a' + 9 {
+        return Err(SgfError);
+    }
+    Ok(Some(GoCoordinates::new(bytes[0] - b'a', bytes[1] - b'a')))
+}
+
+pub fn format_coordinates(coordinates: Option<GoCoordinates>) -> String {
+    match coordinates {
+        Some(coordinates) => format!("{}{}", (b'a' + coordinates.x()) as char, (b'a' + coordinates.y()) as char),
+        None => String::new(),
+    }
+}
+
+/// Parses a point or a compressed rectangle of points like `aa:cc`.
+pub fn parse_point_list(value: &str) -> Result<Vec<GoCoordinates>, SgfError> {
+    let mut parts = value.split(':');
+    let first = parse_coordinates(parts.next().ok_or(SgfError)?)?.ok_or(SgfError)?;
+    let last = match parts.next() {
+        Some(part) => parse_coordinates(part)?.ok_or(SgfError)?,
+        None => first,
+    };
+
+    let mut result = Vec::new();
+    for x in first.x().min(last.x())..=first.x().max(last.x()) {
+        for y in first.y().min(last.y())..=first.y().max(last.y()) {
+            result.push(GoCoordinates::new(x, y));
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_game, parse, parse_point_list, to_sgf};
+    use crate::go::board::{GoBoard, GoCoordinates, GoStone};
+    use crate::go::GoGame;
+
+    #[test]
+    fn test_parse_and_write() {
+        let text = "(;GM[1]SZ[9]C[a \\] b]AB[aa][bb:bc];B[cc](;W[dd])(;W[tt]))";
+        let root = parse(text).unwrap();
+
+        assert_eq!(root.property("C"), Some("a ] b"));
+        assert_eq!(root.main_line().len(), 3);
+        assert_eq!(root.children()[0].children().len(), 2);
+        assert_eq!(root.main_line_moves().unwrap(), vec![
+            (GoStone::BLACK, Some(GoCoordinates::new(2, 2))),
+            (GoStone::WHITE, Some(GoCoordinates::new(3, 3))),
+        ]);
+
+        let mut board = GoBoard::new();
+        root.apply_setup(&mut board).unwrap();
+        assert_eq!(board.count(GoStone::BLACK), 3);
+
+        assert_eq!(parse(&to_sgf(&root)).unwrap(), root);
+        assert_eq!(parse_point_list("aa:bc").unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_resigned_game() {
+        let mut game = GoGame::new();
+        game.play_stone(GoCoordinates::new(4, 4)).unwrap();
+        game.pass();
+        game.resign(GoStone::BLACK);
+
+        let root = parse(&to_sgf(&from_game(&game, 7.0))).unwrap();
+        assert_eq!(root.property("RE"), Some("W+R"));
+        assert_eq!(root.winner(), Some(GoStone::WHITE));
+        assert_eq!(root.main_line_moves().unwrap(), vec![
+            (GoStone::BLACK, Some(GoCoordinates::new(4, 4))),
+            (GoStone::WHITE, None),
+        ]);
+    }
+}
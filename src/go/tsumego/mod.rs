@@ -0,0 +1,439 @@
+use crate::go::board::{GoBoard, GoBoardMask, GoCoordinates, GoStone};
+use crate::go::board::analysis::benson::is_unconditionally_alive;
+use crate::go::board::analysis::chains;
+use crate::go::board::analysis::seki::is_in_seki;
+use crate::go::board::zobrist::{self, splitmix64};
+use crate::go::ai::transposition::{Bound, TableEntry, TranspositionTable};
+use crate::go::sgf::SgfNode;
+use std::sync::Arc;
+
+pub const DEFAULT_MAX_DEPTH: usize = 24;
+pub const SOLUTION_TREE_DEPTH: usize = 6;
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 16;
+
+const PASSED_KEY: u64 = splitmix64(1 << 32);
+
+// Outcomes from the point of view of the attacker, who maximizes.
+const LIVE: i8 = 0;
+const SEKI: i8 = 1;
+const KILL: i8 = 2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TsumegoResult {
+    Kill,
+    Live,
+    Ko,
+    Seki,
+}
+
+/// A life-and-death problem. The defender's target is its largest chain in the region,
+/// and both sides may only play on the empty points of the region.
+#[derive(Clone, Debug)]
+pub struct TsumegoProblem {
+    board: GoBoard,
+    region: GoBoardMask,
+    attacker: GoStone,
+    to_move: GoStone,
+    target: GoCoordinates,
+}
+
+/// A move, where `None` is a pass, and the continuations the solver considered.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolutionTree {
+    coordinates: Option<GoCoordinates>,
+    children: Vec<SolutionTree>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TsumegoSolution {
+    result: TsumegoResult,
+    first_move: Option<GoCoordinates>,
+    tree: Vec<SolutionTree>,
+}
+
+#[derive(Clone, Debug)]
+struct SearchState {
+    board: GoBoard,
+    to_move: GoStone,
+    passed: bool,
+    history: Vec<GoBoard>,
+}
+
+/// Alpha-beta search with a transposition table and iterative deepening.
+/// Positions not resolved within the depth limit count as alive.
+pub struct TsumegoSolver {
+    max_depth: usize,
+    table: Arc<TranspositionTable>,
+    problem: Option<TsumegoProblem>,
+    problem_key: u64,
+}
+
+impl TsumegoProblem {
+    /// `None` if no group of the defender is in or next to `region`.
+    pub fn new(board: GoBoard, region: GoBoardMask, attacker: GoStone, to_move: GoStone) -> Option<Self> {
+        assert!(!attacker.is_none());
+        assert!(!to_move.is_none());
+
+        let target = chains(&board, attacker.opponent_color()).into_iter()
+            .filter(|chain| chain.iter().any(|&c| region.get(c) || c.neighbors().iter().any(|&n| region.get(n))))
+            .max_by_key(|chain| chain.len())?[0];
+
+        Some(Self {board, region, attacker, to_move, target})
+    }
+
+    /// Reads the setup stones and the `PL` property. The region is the bounding box
+    /// of the defender's stones grown by one point. `None` if the setup is invalid or has no defender stones.
+    pub fn from_sgf(root: &SgfNode, attacker: GoStone) -> Option<Self> {
+        let mut board = GoBoard::new();
+        root.apply_setup(&mut board).ok()?;
+        let to_move = root.player_to_move().unwrap_or(GoStone::BLACK);
+        let defender = attacker.opponent_color();
+
+        let stones: Vec<_> = (0..9 * 9).map(GoCoordinates::from).filter(|&c| board.get_stone(c) == defender).collect();
+        if stones.is_empty() {
+            return None;
+        }
+        let min_x = stones.iter().map(|c| c.x()).min().unwrap().saturating_sub(1);
+        let max_x = (stones.iter().map(|c| c.x()).max().unwrap() + 1).min(8);
+        let min_y = stones.iter().map(|c| c.y()).min().unwrap().saturating_sub(1);
+        let max_y = (stones.iter().map(|c| c.y()).max().unwrap() + 1).min(8);
+
+        let mut region = GoBoardMask::new();
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                region.set(GoCoordinates::new(x, y), true);
+            }
+        }
+
+        Self::new(board, region, attacker, to_move)
+    }
+
+    pub fn board(&self) -> &GoBoard {
+        &self.board
+    }
+
+    pub fn region(&self) -> &GoBoardMask {
+        &self.region
+    }
+
+    pub fn attacker(&self) -> GoStone {
+        self.attacker
+    }
+
+    pub fn defender(&self) -> GoStone {
+        self.attacker.opponent_color()
+    }
+
+    pub fn to_move(&self) -> GoStone {
+        self.to_move
+    }
+
+    pub fn target(&self) -> GoCoordinates {
+        self.target
+    }
+}
+
+impl SolutionTree {
+    pub fn coordinates(&self) -> Option<GoCoordinates> {
+        self.coordinates
+    }
+
+    pub fn children(&self) -> &[SolutionTree] {
+        &self.children
+    }
+}
+
+impl TsumegoSolution {
+    pub fn result(&self) -> TsumegoResult {
+        self.result
+    }
+
+    /// The best move for the side to move, `None` if passing is best.
+    pub fn first_move(&self) -> Option<GoCoordinates> {
+        self.first_move
+    }
+
+    /// The correct first move if the side to move achieves the result, otherwise
+    /// all its moves, each followed by the refutation.
+    pub fn tree(&self) -> &[SolutionTree] {
+        &self.tree
+    }
+}
+
+impl TsumegoSolver {
+    pub fn new(max_depth: usize) -> Self {
+        Self::with_table(max_depth, Arc::new(TranspositionTable::new(DEFAULT_TABLE_SIZE)))
+    }
+
+    /// A solver that shares `table` with other searches. Its keys include the problem,
+    /// so entries of different problems do not mix.
+    pub fn with_table(max_depth: usize, table: Arc<TranspositionTable>) -> Self {
+        Self {max_depth, table, problem: None, problem_key: 0}
+    }
+
+    pub fn solve(&mut self, problem: &TsumegoProblem) -> TsumegoSolution {
+        self.table.new_search();
+        self.problem = Some(problem.clone());
+        self.problem_key = problem_key(problem);
+        let root = SearchState {board: problem.board.clone(), to_move: problem.to_move, passed: false, history: vec![problem.board.clone()]};
+
+        let mut value = LIVE;
+        let mut depth = 0;
+        while depth < self.max_depth {
+            depth += 1;
+            value = self.search(&root, depth, -1, KILL + 1);
+            if value == KILL {
+                break;
+            }
+        }
+
+        let first_move = self.table.get(self.key(&root)).and_then(|entry| entry.best_move).flatten();
+        let winner = if value == KILL {problem.attacker} else {problem.defender()};
+        let tree = self.build_tree(&root, depth, SOLUTION_TREE_DEPTH, winner, value);
+
+        let result = match value {
+            KILL if self.principal_variation_has_ko(&root) => TsumegoResult::Ko,
+            KILL => TsumegoResult::Kill,
+            SEKI => TsumegoResult::Seki,
+            _ if self.principal_variation_has_ko(&root) => TsumegoResult::Ko,
+            _ => TsumegoResult::Live,
+        };
+
+        TsumegoSolution {result, first_move, tree}
+    }
+
+    fn problem(&self) -> &TsumegoProblem {
+        self.problem.as_ref().unwrap()
+    }
+
+    fn key(&self, state: &SearchState) -> u64 {
+        let passed = if state.passed {PASSED_KEY} else {0};
+        zobrist::hash(&state.board, state.to_move) ^ passed ^ self.problem_key
+    }
+
+    fn terminal_value(&self, board: &GoBoard) -> Option<i8> {
+        let problem = self.problem();
+        if board.get_stone(problem.target) != problem.defender() {
+            Some(KILL)
+        } else if is_unconditionally_alive(board, problem.target) {
+            Some(LIVE)
+        } else {
+            None
+        }
+    }
+
+    /// Both sides passed without the target being captured.
+    fn double_pass_value(&self, board: &GoBoard) -> i8 {
+        if is_in_seki(board, self.problem().target) {SEKI} else {LIVE}
+    }
+
+    fn children(&self, state: &SearchState) -> Vec<(Option<GoCoordinates>, SearchState)> {
+        let mut result = Vec::new();
+
+        for i in 0..9 * 9 {
+            let coordinates = GoCoordinates::from(i);
+            if !self.problem().region.get(coordinates) {
+                continue;
+            }
+
+            if let Some((board, _)) = state.board.try_play(coordinates, state.to_move) {
+                if state.history.contains(&board) {
+                    continue;
+                }
+                let mut history = state.history.clone();
+                history.push(board.clone());
+                result.push((Some(coordinates), SearchState {board, to_move: state.to_move.opponent_color(), passed: false, history}));
+            }
+        }
+
+        let pass = SearchState {board: state.board.clone(), to_move: state.to_move.opponent_color(), passed: true, history: state.history.clone()};
+        result.push((None, pass));
+        result
+    }
+
+    fn child_value(&mut self, state: &SearchState, child: &SearchState, depth: usize, alpha: i8, beta: i8) -> i8 {
+        if state.passed && child.passed {
+            self.double_pass_value(&child.board)
+        } else {
+            self.search(child, depth - 1, alpha, beta)
+        }
+    }
+
+    fn search(&mut self, state: &SearchState, depth: usize, mut alpha: i8, mut beta: i8) -> i8 {
+        if let Some(value) = self.terminal_value(&state.board) {
+            return value;
+        }
+        if depth == 0 {
+            return LIVE;
+        }
+
+        let key = self.key(state);
+        let mut best_move = None;
+        if let Some(entry) = self.table.get(key) {
+            best_move = entry.best_move;
+            let value = entry.value as i8;
+            if entry.depth as usize >= depth {
+                match entry.bound {
+                    Bound::Exact => return value,
+                    Bound::Lower => alpha = alpha.max(value),
+                    Bound::Upper => beta = beta.min(value),
+                }
+                if alpha >= beta {
+                    return value;
+                }
+            }
+        }
+
+        let (original_alpha, original_beta) = (alpha, beta);
+        let maximizing = state.to_move == self.problem().attacker;
+        let mut children = self.children(state);
+        if let Some(best_move) = best_move {
+            if let Some(index) = children.iter().position(|(coordinates, _)| *coordinates == best_move) {
+                let child = children.remove(index);
+                children.insert(0, child);
+            }
+        }
+
+        let mut best_value = if maximizing {LIVE - 1} else {KILL + 1};
+        for (coordinates, child) in children {
+            let value = self.child_value(state, &child, depth, alpha, beta);
+
+            if (maximizing && value > best_value) || (!maximizing && value < best_value) {
+                best_value = value;
+                best_move = Some(coordinates);
+            }
+            if maximizing {
+                alpha = alpha.max(value);
+            } else {
+                beta = beta.min(value);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_value <= original_alpha {
+            Bound::Upper
+        } else if best_value >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(key, TableEntry::searched(depth as u16, best_value as f32, bound, best_move));
+        best_value
+    }
+
+    fn build_tree(&mut self, state: &SearchState, depth: usize, tree_depth: usize, winner: GoStone, value: i8) -> Vec<SolutionTree> {
+        if tree_depth == 0 || depth == 0 || self.terminal_value(&state.board).is_some() {
+            return Vec::new();
+        }
+
+        let mut result = Vec::new();
+        for (coordinates, child) in self.children(state) {
+            let child_value = self.child_value(state, &child, depth, -1, KILL + 1);
+            let finished = state.passed && child.passed;
+
+            if state.to_move == winner {
+                let achieves = if winner == self.problem().attacker {child_value >= value} else {child_value <= value};
+                if achieves {
+                    let children = if finished {Vec::new()} else {self.build_tree(&child, depth - 1, tree_depth - 1, winner, value)};
+                    return vec![SolutionTree {coordinates, children}];
+                }
+            } else {
+                let children = if finished {Vec::new()} else {self.build_tree(&child, depth - 1, tree_depth - 1, winner, value)};
+                result.push(SolutionTree {coordinates, children});
+            }
+        }
+
+        result
+    }
+
+    /// Follows the best moves from the table and looks for a stone captured by a single
+    /// stone that could immediately be taken back.
+    fn principal_variation_has_ko(&self, root: &SearchState) -> bool {
+        let mut state = root.clone();
+
+        for _ in 0..self.max_depth {
+            let coordinates = match self.table.get(self.key(&state)).and_then(|entry| entry.best_move) {
+                Some(Some(coordinates)) => coordinates,
+                Some(None) if !state.passed => {
+                    state.passed = true;
+                    state.to_move = state.to_move.opponent_color();
+                    continue;
+                }
+                _ => return false,
+            };
+
+            let (board, captured) = match state.board.try_play(coordinates, state.to_move) {
+                Some(result) => result,
+                None => return false,
+            };
+            if captured == 1 && board.group(coordinates).len() == 1 && board.liberties(coordinates).len() == 1 {
+                return true;
+            }
+
+            state.board = board;
+            state.to_move = state.to_move.opponent_color();
+            state.passed = false;
+            if self.terminal_value(&state.board).is_some() {
+                return false;
+            }
+        }
+
+        false
+    }
+}
+
+/// Distinguishes the table entries of different problems on the same board.
+fn problem_key(problem: &TsumegoProblem) -> u64 {
+    let mut result = splitmix64(usize::from(problem.target) as u64 ^ ((problem.attacker as u64) << 8));
+    for i in 0..9 * 9 {
+        if problem.region.get(GoCoordinates::from(i)) {
+            result = splitmix64(result ^ i as u64);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_MAX_DEPTH, TsumegoProblem, TsumegoResult, TsumegoSolver};
+    use crate::go::board::GoStone;
+    use crate::go::sgf::{parse, parse_coordinates};
+
+    const PROBLEMS: &[(&str, TsumegoResult, &str)] = &[
+        (include_str!("problems/straight_three_kill.sgf"), TsumegoResult::Kill, "ba"),
+        (include_str!("problems/straight_three_live.sgf"), TsumegoResult::Live, "ba"),
+        (include_str!("problems/bent_three_kill.sgf"), TsumegoResult::Kill, "aa"),
+        (include_str!("problems/straight_four_alive.sgf"), TsumegoResult::Live, ""),
+        (include_str!("problems/edge_ko.sgf"), TsumegoResult::Ko, "da"),
+        (include_str!("problems/edge_seki.sgf"), TsumegoResult::Seki, ""),
+    ];
+
+    #[test]
+    fn test_classic_problems() {
+        let mut solver = TsumegoSolver::new(DEFAULT_MAX_DEPTH);
+
+        for &(sgf, result, first_move) in PROBLEMS {
+            let root = parse(sgf).unwrap();
+            let problem = TsumegoProblem::from_sgf(&root, GoStone::BLACK).unwrap();
+            let solution = solver.solve(&problem);
+
+            assert_eq!(solution.result(), result, "{}", root.property("C").unwrap());
+            if !first_move.is_empty() {
+                assert_eq!(solution.first_move(), parse_coordinates(first_move).unwrap(), "{}", root.property("C").unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_refutation_tree() {
+        let root = parse(PROBLEMS[0].0).unwrap();
+        let problem = TsumegoProblem::from_sgf(&root, GoStone::BLACK).unwrap();
+        let solution = TsumegoSolver::new(DEFAULT_MAX_DEPTH).solve(&problem);
+
+        assert_eq!(solution.tree().len(), 1);
+        assert_eq!(solution.tree()[0].coordinates(), solution.first_move());
+        assert!(!solution.tree()[0].children().is_empty());
+    }
+}
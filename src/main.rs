@@ -1,13 +1,33 @@
 use ggez::{Context, ContextBuilder, GameResult};
-use ggez::event::{self, EventHandler, MouseButton};
+use ggez::event::{self, EventHandler, KeyCode, KeyMods, MouseButton};
 use ggez::graphics;
-use go::GoGame;
-use go::ai::{GoAI, voronoi_ai::VoronoiAI};
-use go::board::{GoStone, GoCoordinates};
+use go::{GoGame, IllegalMove};
+use go::ai::{available_ai_names, create_ai, load_weights, GoAI, MoveAnalysis, ResignPolicy, AI_NAMES, DEFAULT_RESIGN_MOVES, DEFAULT_RESIGN_THRESHOLD, PUCT_AI, WEIGHTS_FILE};
+use go::ai::mcts_ai::MctsAI;
+use go::book::{BookAI, OpeningBook, DEFAULT_MAX_DEPTH};
+use go::clock::GameClock;
+use go::board::{GoStone, GoCoordinates, GoBoard, GoBoardMask};
+use go::board::influence::{InfluenceClass, TerritoryEstimate, INITIAL_INFLUENCE};
+use go::playout::RandomPolicy;
+use go::playout::ownership::{estimate_ownership, OwnershipMap};
+use go::scoring::{score, territory, GameScore};
+use go::sgf::{from_game, to_sgf};
 use ui::button::Button;
+use ui::history::HistoryPanel;
+use ui::legend::Legend;
+use ui::panel::{self, row};
 use ui::label::Label;
+use ui::settings::{GameSettings, SettingsResult, SettingsScreen, HUMAN};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use go_ai::go;
 
-pub mod go;
 pub mod ui;
 
 const BACKGROUND_COLOR: graphics::Color = graphics::Color::new(0.831, 0.776, 0.509, 1.0);
@@ -16,11 +36,80 @@ const WHITE_STONE_COLOR: graphics::Color = graphics::Color::new(0.9, 0.9, 0.9, 1
 const BLACK_STONE_COLOR: graphics::Color = graphics::Color::new(0.1, 0.1, 0.1, 1.0);
 const NO_TEINT: graphics::Color = graphics::Color::WHITE;
 const GHOST_TEINT: graphics::Color = graphics::Color::new(1.0, 1.0, 1.0, 0.5);
+const TERRITORY_ALPHA: f32 = 0.6;
+const MOYO_ALPHA: f32 = 0.25;
+const KO_COLOR: graphics::Color = graphics::Color::new(0.8, 0.1, 0.1, 1.0);
+const FORBIDDEN_COLOR: graphics::Color = graphics::Color::new(0.8, 0.1, 0.1, 0.8);
+const CAPTURE_COLOR: graphics::Color = graphics::Color::new(0.9, 0.4, 0.1, 1.0);
+const TOOLTIP_COLOR: graphics::Color = graphics::Color::new(1.0, 1.0, 0.9, 0.9);
+const CANDIDATE_COLOR: graphics::Color = graphics::Color::new(0.2, 0.5, 0.9, 0.8);
+const BEST_CANDIDATE_COLOR: graphics::Color = graphics::Color::new(0.2, 0.7, 0.3, 0.9);
+const ANALYSIS_FONT_SIZE: f32 = 13.0;
+/// The space around the board for the coordinates.
+const BOARD_MARGIN: f32 = 20.0;
+
+const OPENING_BOOK_FILE: &str = "opening_book.txt";
+const SETTINGS_FILE: &str = "settings.txt";
+/// Finished games are saved here as SGF files named after the time they ended.
+const GAMES_DIRECTORY: &str = "games";
+
+/// The pause between moves when two AIs play each other, so the game can be followed.
+const AI_VS_AI_DELAY: Duration = Duration::from_millis(500);
+const DELAY_STEP: Duration = Duration::from_millis(100);
+
+/// The playouts that guess the dead stones when the scoring phase starts.
+const SCORING_PLAYOUTS: usize = 200;
+const SCORING_THREADS: usize = 2;
+/// The playouts of the ownership overlay, which is computed again for every position.
+const OWNERSHIP_PLAYOUTS: usize = 500;
+/// The strongest shading of the influence and ownership heatmaps.
+const HEATMAP_ALPHA: f32 = 0.7;
+/// How often the analysis mode shows the candidates of its background search.
+const ANALYSIS_REFRESH: Duration = Duration::from_millis(500);
+const ANALYSIS_CANDIDATES: usize = 5;
+
+/// The players given on the command line replace the saved ones for this run.
+const USAGE: &str = "Usage: go_ai [--black human|AI] [--white human|AI] [--delay MS]";
 
 const WINDOW_WIDTH: u16 = 800;
 const WINDOW_HEIGHT: u16 = 600;
 
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    eprintln!("{}", USAGE);
+    eprintln!("AIs: {}", available_ai_names().join(", "));
+    process::exit(1);
+}
+
+fn weights_missing(error: &io::Error) -> String {
+    format!("The {} AI needs trained weights in {}: {}", PUCT_AI, WEIGHTS_FILE, error)
+}
+
 fn main() {
+    let mut settings = GameSettings::load_file(SETTINGS_FILE).unwrap_or_else(|_| GameSettings::new());
+    let mut delay = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| fail(format!("Missing value for {}", arg)));
+        match arg.as_str() {
+            "--black" | "--white" => {
+                if value == PUCT_AI {
+                    if let Err(error) = load_weights() {
+                        fail(weights_missing(&error));
+                    }
+                } else if value != HUMAN && !AI_NAMES.contains(&value.as_str()) {
+                    fail(format!("Unknown player {}", value));
+                }
+                settings.players[if arg == "--black" {0} else {1}] = value;
+            }
+            "--delay" => delay = Some(Duration::from_millis(value.parse().unwrap_or_else(|_| fail(format!("Invalid value for {}: {}", arg, value))))),
+            _ => fail(format!("Unknown option {}", arg)),
+        }
+    }
+
+    let book = OpeningBook::load_file(OPENING_BOOK_FILE).unwrap_or_else(|_| OpeningBook::new(DEFAULT_MAX_DEPTH));
+
     // Make a Context and an EventLoop.
     let (mut ctx, event_loop) =
         ContextBuilder::new("Go AI", "Sebastian Schmidt")
@@ -30,7 +119,7 @@ fn main() {
     // Create an instance of your event handler.
     // Usually, you should provide it with the Context object
     // so it can load resources like images during setup.
-    let my_game = MyGame::new(&mut ctx, VoronoiAI::new());
+    let my_game = MyGame::new(&mut ctx, settings, book, delay);
 
     // Run!
     /*match event::run(ctx, event_loop, my_game) {
@@ -41,41 +130,830 @@ fn main() {
     event::run(ctx, event_loop, my_game)
 }
 
-struct MyGame<AI> {
+/// Who plays one color: a human at the board, or an AI that plays from the opening book first.
+/// The `ai` is `None` while it searches its move on a worker thread.
+enum Player {
+    Human,
+    AI {name: String, ai: Option<Box<dyn GoAI + Send>>, resign_policy: ResignPolicy},
+}
+
+/// The search of an AI on a worker thread, which hands the AI back with its move.
+type Thinking = JoinHandle<(Box<dyn GoAI + Send>, Option<GoCoordinates>)>;
+
+impl Player {
+    /// `human` or one of `available_ai_names`.
+    fn create(name: &str, book: &OpeningBook, komi: f32, seed: u64) -> Option<Self> {
+        if name == HUMAN {
+            return Some(Player::Human);
+        }
+
+        let mut ai = BookAI::new(book.clone(), create_ai(name, seed)?);
+        ai.set_komi(komi);
+        Some(Player::AI {name: name.to_string(), ai: Some(Box::new(ai)), resign_policy: ResignPolicy::new(DEFAULT_RESIGN_THRESHOLD, DEFAULT_RESIGN_MOVES)})
+    }
+
+    /// Like `create`, but a human takes the seat of an AI that can not be created, and the reason is printed.
+    fn create_or_human(name: &str, book: &OpeningBook, komi: f32, seed: u64) -> Self {
+        Self::create(name, book, komi, seed).unwrap_or_else(|| {
+            match load_weights() {
+                Err(error) if name == PUCT_AI => eprintln!("{}", weights_missing(&error)),
+                _ => eprintln!("Can not create the {} AI", name),
+            }
+            eprintln!("A human plays instead");
+            Player::Human
+        })
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Player::Human => HUMAN,
+            Player::AI {name, ..} => name,
+        }
+    }
+
+    fn is_human(&self) -> bool {
+        matches!(self, Player::Human)
+    }
+
+    /// The AI, unless it is a human or searching its move.
+    fn ai(&mut self) -> Option<&mut Box<dyn GoAI + Send>> {
+        match self {
+            Player::AI {ai, ..} => ai.as_mut(),
+            Player::Human => None,
+        }
+    }
+}
+
+fn player_index(color: GoStone) -> usize {
+    if color == GoStone::BLACK {0} else {1}
+}
+
+/// The background search of the analysis mode, which follows the shown position.
+struct Analysis {
+    ai: MctsAI,
+    /// The number of moves and the board of the analysed position.
+    position: (usize, GoBoard),
+    /// The player to move in the analysed position.
+    turn: GoStone,
+    candidates: Vec<MoveAnalysis>,
+    last_refresh: Instant,
+}
+
+/// What is shaded on the points of the board to show how the AIs see the position.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Overlay {
+    /// Bouzy territory and moyo.
+    Territory,
+    /// The nearest stones, from `GoBoard::voronoi`.
+    Voronoi,
+    /// The Bouzy influence of each color.
+    Influence,
+    /// The probability that each color owns a point at the end of random playouts.
+    Ownership,
+}
+
+impl Overlay {
+    fn name(self) -> &'static str {
+        match self {
+            Overlay::Territory => "Territory",
+            Overlay::Voronoi => "Voronoi",
+            Overlay::Influence => "Influence",
+            Overlay::Ownership => "Ownership",
+        }
+    }
+
+    fn legend(self) -> Legend {
+        let coordinates = panel::position(row::LEGEND, 0.0);
+        match self {
+            Overlay::Voronoi => Legend::new(&[(BLACK_STONE_COLOR, "Black area"), (WHITE_STONE_COLOR, "White area")], false, coordinates),
+            _ => Legend::new(&[(BLACK_STONE_COLOR, "Black"), (WHITE_STONE_COLOR, "White")], true, coordinates),
+        }
+    }
+}
+
+struct MyGame {
     grid: [f32; 9],
     grid_box_len: f32,
+    coordinate_labels: Vec<Label>,
     game: GoGame,
     mouse_x: f32,
     mouse_y: f32,
     undo_button: Button,
+    pass_button: Button,
+    resign_button: Button,
+    new_game_button: Button,
     black_captures_label: Label,
     white_captures_label: Label,
-    ai: AI,
+    score_label: Label,
+    overlay_label: Label,
+    overlay: Option<Overlay>,
+    legend: Option<Legend>,
+    /// The ownership of the position it was estimated for, while the ownership overlay is shown.
+    ownership: Option<(GoBoard, OwnershipMap)>,
+    clock: GameClock,
+    black_clock_label: Label,
+    white_clock_label: Label,
+    players: [Player; 2],
+    black_player_label: Label,
+    white_player_label: Label,
+    paused: bool,
+    step: bool,
+    move_delay: Duration,
+    last_move_time: Instant,
+    pause_label: Label,
+    step_label: Label,
+    delay_label: Label,
+    settings: GameSettings,
+    settings_screen: Option<SettingsScreen>,
+    /// Whether the game was running when the settings screen paused it.
+    resume_after_settings: bool,
+    book: OpeningBook,
+    /// The delay given on the command line, which replaces the default of each new game.
+    fixed_delay: Option<Duration>,
+    /// The stones marked dead, from the two passes that start the scoring phase until the next game.
+    dead_stones: Option<GoBoardMask>,
+    done_button: Button,
+    resume_button: Button,
+    scoring_label: Label,
+    result_panel: Vec<Label>,
+    thinking: Option<Thinking>,
+    /// The last score with the position, captures and dead stones it was counted for.
+    score: Option<((GoBoard, u64, u64, GoBoardMask), GameScore)>,
+    history: HistoryPanel,
+    show_move_numbers: bool,
+    move_numbers_label: Label,
+    analysis: Option<Analysis>,
+    analysis_label: Label,
 }
 
-impl<AI: GoAI> MyGame<AI> {
-    pub fn new(_ctx: &mut Context, mut ai: AI) -> Self {
+impl MyGame {
+    pub fn new(_ctx: &mut Context, settings: GameSettings, book: OpeningBook, fixed_delay: Option<Duration>) -> Self {
         // Load/create resources here: images, fonts, sounds, etc.
         let mut grid = [0.0; 9];
 
         let square_side_len = WINDOW_WIDTH.min(WINDOW_HEIGHT) as f32;
-        let grid_box_len = square_side_len / 9.0;
+        let grid_box_len = (square_side_len - 2.0 * BOARD_MARGIN) / 9.0;
         for i in 0..9 {
-            grid[i] = (i as f32 + 0.5) * grid_box_len;
+            grid[i] = BOARD_MARGIN + (i as f32 + 0.5) * grid_box_len;
         }
 
-        let undo_button = Button::new(Label::new("Undo", [610.0, 10.0].into()));
-        let black_captures_label = Label::new("Black captures: 0", [610.0, 30.0].into());
-        let white_captures_label = Label::new("White captures: 0", [610.0, 50.0].into());
+        // Column letters above and below the board and row numbers counted from the bottom on both sides.
+        let mut coordinate_labels = Vec::new();
+        for (i, &line) in grid.iter().enumerate() {
+            let column = GoCoordinates::new_usize(i, 8).to_string();
+            let row = (9 - i).to_string();
+            coordinate_labels.push(Label::new(&column[..1], [line - 4.0, 2.0].into()));
+            coordinate_labels.push(Label::new(&column[..1], [line - 4.0, square_side_len - 18.0].into()));
+            coordinate_labels.push(Label::new(&row, [5.0, line - 8.0].into()));
+            coordinate_labels.push(Label::new(&row, [square_side_len - 14.0, line - 8.0].into()));
+        }
 
-        let game = GoGame::new();
-        ai.set_game(game.clone());
+        let undo_button = Button::new(Label::new("Undo", panel::position(row::BUTTONS, 0.0)));
+        let pass_button = Button::new(Label::new("Pass", panel::position(row::BUTTONS, 40.0)));
+        let resign_button = Button::new(Label::new("Resign", panel::position(row::BUTTONS, 80.0)));
+        let new_game_button = Button::new(Label::new("New", panel::position(row::BUTTONS, 140.0)));
+        let black_captures_label = Label::new("Black captures: 0", panel::position(row::BLACK_CAPTURES, 0.0));
+        let white_captures_label = Label::new("White captures: 0", panel::position(row::WHITE_CAPTURES, 0.0));
+        let score_label = Label::new("Score: B 0, W 0", panel::position(row::SCORE, 0.0));
+        let overlay_label = Label::new("[T/V/I/O] Overlay: off", panel::position(row::OVERLAY, 0.0));
+        let black_clock_label = Label::new("Black clock:", panel::position(row::BLACK_CLOCK, 0.0));
+        let white_clock_label = Label::new("White clock:", panel::position(row::WHITE_CLOCK, 0.0));
+        let black_player_label = Label::new("Black:", panel::position(row::BLACK_PLAYER, 0.0));
+        let white_player_label = Label::new("White:", panel::position(row::WHITE_PLAYER, 0.0));
+        let pause_label = Label::new("[Space] Pause", panel::position(row::PAUSE, 0.0));
+        let step_label = Label::new("[N] Step", panel::position(row::STEP, 0.0));
+        let delay_label = Label::new("[+/-] Delay:", panel::position(row::DELAY, 0.0));
+        let done_button = Button::new(Label::new("Done", panel::position(row::RESULT, 0.0)));
+        let resume_button = Button::new(Label::new("Resume", panel::position(row::RESULT, 50.0)));
+        let scoring_label = Label::new("Click dead groups", panel::position(row::SCORING, 0.0));
+        let move_numbers_label = Label::new("[M] Move numbers: off", panel::position(row::MOVE_NUMBERS, 0.0));
+        let analysis_label = Label::new("[A] Analysis: off", panel::position(row::ANALYSIS, 0.0));
 
-        MyGame { grid, grid_box_len, game, mouse_x: -1.0, mouse_y: -1.0, undo_button, black_captures_label, white_captures_label, ai }
+        let mut result = MyGame {
+            grid, grid_box_len, coordinate_labels, game: GoGame::new(), mouse_x: -1.0, mouse_y: -1.0, undo_button, pass_button, resign_button, new_game_button, black_captures_label, white_captures_label,
+            score_label, overlay_label, overlay: None, legend: None, ownership: None, clock: GameClock::new(settings.time_control()), black_clock_label, white_clock_label,
+            players: [Player::Human, Player::Human], black_player_label, white_player_label, paused: false, step: false, move_delay: Duration::from_secs(0),
+            last_move_time: Instant::now(), pause_label, step_label, delay_label, settings: settings.clone(), settings_screen: None, resume_after_settings: false, book, fixed_delay,
+            dead_stones: None, done_button, resume_button, scoring_label, result_panel: Vec::new(), thinking: None, score: None,
+            history: HistoryPanel::new(row::HISTORY, panel::ROWS - row::HISTORY - 3), show_move_numbers: false, move_numbers_label,
+            analysis: None, analysis_label,
+        };
+        result.start_game(settings);
+        result
     }
 }
 
-impl<AI: GoAI> EventHandler for MyGame<AI> {
+impl MyGame {
+    /// Shades the points of the shown position for `overlay`, with the opacity as the strength.
+    fn draw_overlay(&self, ctx: &mut Context, overlay: Overlay) -> GameResult<()> {
+        let board = self.shown_board();
+        let shades: Vec<(GoStone, f32)> = match overlay {
+            Overlay::Territory => {
+                let estimate = TerritoryEstimate::new(board);
+                (0..9 * 9).map(|i| match estimate.classify(GoCoordinates::from(i)) {
+                    InfluenceClass::Territory(color) => (color, TERRITORY_ALPHA),
+                    InfluenceClass::Moyo(color) => (color, MOYO_ALPHA),
+                    InfluenceClass::Neutral => (GoStone::NONE, 0.0),
+                }).collect()
+            }
+            Overlay::Voronoi => {
+                let voronoi = board.voronoi();
+                (0..9 * 9).map(|i| (voronoi.get_stone(GoCoordinates::from(i)), TERRITORY_ALPHA)).collect()
+            }
+            Overlay::Influence => {
+                let estimate = TerritoryEstimate::new(board);
+                (0..9 * 9).map(|i| {
+                    let influence = estimate.moyo_map().get(GoCoordinates::from(i));
+                    let color = if influence > 0 {GoStone::BLACK} else if influence < 0 {GoStone::WHITE} else {GoStone::NONE};
+                    (color, (influence.abs() as f32 / INITIAL_INFLUENCE as f32).min(1.0) * HEATMAP_ALPHA)
+                }).collect()
+            }
+            Overlay::Ownership => match &self.ownership {
+                Some((_, ownership)) => (0..9 * 9).map(|i| {
+                    let value = ownership.ownership(GoCoordinates::from(i));
+                    (if value >= 0.0 {GoStone::BLACK} else {GoStone::WHITE}, value.abs() * HEATMAP_ALPHA)
+                }).collect(),
+                None => return Ok(()),
+            },
+        };
+
+        let mut overlay = graphics::MeshBuilder::new();
+        let half_side = self.grid_box_len / 4.0;
+        for (i, (color, alpha)) in shades.into_iter().enumerate() {
+            if color.is_none() || alpha <= 0.0 {
+                continue;
+            }
+            let c = GoCoordinates::from(i);
+            let mut color = if color == GoStone::BLACK {BLACK_STONE_COLOR} else {WHITE_STONE_COLOR};
+            color.a = alpha;
+
+            let bounds = graphics::Rect::new(self.grid[c.x() as usize] - half_side, self.grid[c.y() as usize] - half_side, 2.0 * half_side, 2.0 * half_side);
+            overlay.rectangle(graphics::DrawMode::fill(), bounds, color)?;
+        }
+
+        if let Ok(overlay) = overlay.build(ctx) {
+            graphics::draw(ctx, &overlay, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, NO_TEINT))?;
+        }
+        Ok(())
+    }
+
+    /// Shows `overlay`, or hides it if it is already shown.
+    fn toggle_overlay(&mut self, overlay: Overlay) {
+        self.overlay = if self.overlay == Some(overlay) {None} else {Some(overlay)};
+        self.legend = self.overlay.map(Overlay::legend);
+        self.ownership = None;
+    }
+
+    /// Estimates the ownership again when the shown position changed.
+    fn update_ownership(&mut self) {
+        if self.overlay != Some(Overlay::Ownership) || self.ownership.as_ref().is_some_and(|(board, _)| board == self.shown_board()) {
+            return;
+        }
+
+        let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let ownership = estimate_ownership(&self.shown_game(), &RandomPolicy, OWNERSHIP_PLAYOUTS, SCORING_THREADS, seed);
+        self.ownership = Some((self.shown_board().clone(), ownership));
+    }
+
+    /// Starts searching the shown position in the background, or stops the analysis.
+    fn toggle_analysis(&mut self) {
+        if self.analysis.take().is_some() {
+            return;
+        }
+
+        let mut ai = MctsAI::new();
+        ai.set_komi(self.settings.komi);
+        // No position has that many moves, so the next update starts the search.
+        self.analysis = Some(Analysis {
+            ai, position: (usize::MAX, GoBoard::new()), turn: GoStone::BLACK, candidates: Vec::new(), last_refresh: Instant::now(),
+        });
+    }
+
+    /// Restarts the analysis when the shown position changed, and otherwise takes the
+    /// candidates found so far at regular intervals while the search goes on.
+    fn update_analysis(&mut self) {
+        let position = (self.history.viewed().unwrap_or(self.game.moves().len()), self.shown_board().clone());
+        let game = match &self.analysis {
+            Some(analysis) if analysis.position != position => Some(self.shown_game()),
+            Some(_) => None,
+            None => return,
+        };
+        let analysis = self.analysis.as_mut().unwrap();
+
+        if let Some(game) = game {
+            analysis.turn = game.current_turn();
+            analysis.ai.set_game(game);
+            analysis.ai.start_pondering();
+            analysis.position = position;
+            analysis.candidates.clear();
+            analysis.last_refresh = Instant::now();
+        } else if analysis.last_refresh.elapsed() >= ANALYSIS_REFRESH {
+            analysis.ai.stop_pondering();
+            analysis.candidates = analysis.ai.analysis();
+            analysis.ai.start_pondering();
+            analysis.last_refresh = Instant::now();
+        }
+    }
+
+    /// Marks the best candidates of the analysis with their win rate, score lead and visits, or
+    /// shows the expected continuation as numbered ghost stones while a candidate is hovered.
+    fn draw_analysis(&self, ctx: &mut Context, analysis: &Analysis, black_stone: &graphics::Mesh, white_stone: &graphics::Mesh) -> GameResult<()> {
+        let board = self.shown_board();
+        let candidates: Vec<_> = analysis.candidates.iter()
+            .filter(|candidate| candidate.game_move.is_some_and(|c| board.get_stone(c).is_none()))
+            .take(ANALYSIS_CANDIDATES)
+            .collect();
+        let hovered = self.point_at(self.mouse_x, self.mouse_y);
+
+        if let Some(candidate) = candidates.iter().find(|candidate| hovered.is_some() && candidate.game_move == hovered) {
+            let mut color = analysis.turn;
+            let mut numbers = vec![None; 9 * 9];
+            for (index, game_move) in candidate.variation.iter().enumerate() {
+                if let Some(c) = game_move {
+                    let stone = if color == GoStone::BLACK {black_stone} else {white_stone};
+                    graphics::draw(ctx, stone, (graphics::mint::Point2::from([self.grid[c.x() as usize], self.grid[c.y() as usize]]), 0.0, GHOST_TEINT))?;
+                    numbers[usize::from(*c)] = Some((index + 1, color));
+                }
+                color = color.opponent_color();
+            }
+
+            for (i, number) in numbers.into_iter().enumerate() {
+                if let Some((number, color)) = number {
+                    let c = GoCoordinates::from(i);
+                    let text = graphics::Text::new(number.to_string());
+                    let bounds = text.dimensions(ctx);
+                    let position = [self.grid[c.x() as usize] - bounds.w / 2.0, self.grid[c.y() as usize] - bounds.h / 2.0];
+                    graphics::queue_text(ctx, &text, position, Some(if color == GoStone::BLACK {WHITE_STONE_COLOR} else {BLACK_STONE_COLOR}));
+                }
+            }
+            return Ok(());
+        }
+
+        let mut marks = graphics::MeshBuilder::new();
+        for (rank, candidate) in candidates.iter().enumerate() {
+            let c = candidate.game_move.unwrap();
+            let center = graphics::mint::Point2::from([self.grid[c.x() as usize], self.grid[c.y() as usize]]);
+            marks.circle(graphics::DrawMode::fill(), center, 0.45 * self.grid_box_len, 0.5, if rank == 0 {BEST_CANDIDATE_COLOR} else {CANDIDATE_COLOR})?;
+
+            let score_lead = candidate.score_lead.map_or(String::new(), |lead| format!("\n{:+.1}", lead));
+            let mut text = graphics::Text::new(format!("{:.0}%{}\n{}", 100.0 * candidate.win_rate, score_lead, candidate.visits));
+            text.set_font(graphics::Font::default(), graphics::PxScale::from(ANALYSIS_FONT_SIZE));
+            let bounds = text.dimensions(ctx);
+            graphics::queue_text(ctx, &text, [center.x - bounds.w / 2.0, center.y - bounds.h / 2.0], Some(graphics::Color::BLACK));
+        }
+
+        if let Ok(marks) = marks.build(ctx) {
+            graphics::draw(ctx, &marks, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, NO_TEINT))?;
+        }
+        Ok(())
+    }
+
+    /// Crosses out the stones a move at `c` would capture, or marks the point as forbidden
+    /// with the reason next to the mouse.
+    fn draw_move_preview(&self, ctx: &mut Context, c: GoCoordinates) -> GameResult<()> {
+        let mut marks = graphics::MeshBuilder::new();
+        let half_side = self.grid_box_len / 5.0;
+        let cross = |marks: &mut graphics::MeshBuilder, c: GoCoordinates, color: graphics::Color| -> GameResult<()> {
+            let (x, y) = (self.grid[c.x() as usize], self.grid[c.y() as usize]);
+            marks.line(&[graphics::mint::Point2::from([x - half_side, y - half_side]), graphics::mint::Point2::from([x + half_side, y + half_side])], 3.0, color)?;
+            marks.line(&[graphics::mint::Point2::from([x - half_side, y + half_side]), graphics::mint::Point2::from([x + half_side, y - half_side])], 3.0, color)?;
+            Ok(())
+        };
+
+        let reason = match self.game.check_move(c) {
+            Ok(captured) => {
+                for stone in captured {
+                    cross(&mut marks, stone, CAPTURE_COLOR)?;
+                }
+                None
+            }
+            // Stones are not previewed on, so an occupied point needs no marker.
+            Err(IllegalMove::Occupied) => None,
+            Err(reason) => {
+                let center = graphics::mint::Point2::from([self.grid[c.x() as usize], self.grid[c.y() as usize]]);
+                marks.circle(graphics::DrawMode::stroke(3.0), center, self.grid_box_len / 3.0, 0.5, FORBIDDEN_COLOR)?;
+                cross(&mut marks, c, FORBIDDEN_COLOR)?;
+                Some(reason)
+            }
+        };
+
+        if let Ok(marks) = marks.build(ctx) {
+            graphics::draw(ctx, &marks, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, NO_TEINT))?;
+        }
+
+        if let Some(reason) = reason {
+            let text = graphics::Text::new(reason.to_string());
+            let mut bounds = text.dimensions(ctx);
+            bounds.x = self.mouse_x + 12.0;
+            bounds.y = self.mouse_y + 12.0;
+            let background = graphics::MeshBuilder::new().rectangle(graphics::DrawMode::fill(), bounds, TOOLTIP_COLOR)?.build(ctx)?;
+            graphics::draw(ctx, &background, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, NO_TEINT))?;
+            graphics::queue_text(ctx, &text, [bounds.x, bounds.y], Some(graphics::Color::BLACK));
+        }
+        Ok(())
+    }
+
+    /// Marks the last move of the shown position, the move numbers of the stones if enabled, and the ko point.
+    fn draw_annotations(&self, ctx: &mut Context) -> GameResult<()> {
+        let shown_moves = self.history.viewed().unwrap_or(self.game.moves().len());
+        let moves = &self.game.moves()[..shown_moves];
+        let board = self.shown_board();
+        let contrast = |c: GoCoordinates| if board.get_stone(c) == GoStone::BLACK {WHITE_STONE_COLOR} else {BLACK_STONE_COLOR};
+        let mut marks = graphics::MeshBuilder::new();
+
+        if let Some(&Some(c)) = moves.last() {
+            let center = graphics::mint::Point2::from([self.grid[c.x() as usize], self.grid[c.y() as usize]]);
+            marks.circle(graphics::DrawMode::stroke(2.0), center, self.grid_box_len / 4.0, 0.5, contrast(c))?;
+        }
+
+        if self.history.viewed().is_none() {
+            let half_side = self.grid_box_len / 4.0;
+            for c in self.game.ko_points() {
+                let bounds = graphics::Rect::new(self.grid[c.x() as usize] - half_side, self.grid[c.y() as usize] - half_side, 2.0 * half_side, 2.0 * half_side);
+                marks.rectangle(graphics::DrawMode::stroke(2.0), bounds, KO_COLOR)?;
+            }
+        }
+
+        if let Ok(marks) = marks.build(ctx) {
+            graphics::draw(ctx, &marks, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, NO_TEINT))?;
+        }
+
+        if self.show_move_numbers {
+            // A point played on again after a capture shows its latest move.
+            let mut numbers = vec![None; 9 * 9];
+            for (index, game_move) in moves.iter().enumerate() {
+                if let Some(c) = game_move {
+                    numbers[usize::from(*c)] = Some(index + 1);
+                }
+            }
+
+            for (i, number) in numbers.into_iter().enumerate() {
+                let c = GoCoordinates::from(i);
+                if let (Some(number), false) = (number, board.get_stone(c).is_none()) {
+                    let text = graphics::Text::new(number.to_string());
+                    let bounds = text.dimensions(ctx);
+                    let position = [self.grid[c.x() as usize] - bounds.w / 2.0, self.grid[c.y() as usize] - bounds.h / 2.0];
+                    graphics::queue_text(ctx, &text, position, Some(contrast(c)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Shades the territory of the scoring phase, counted without the dead stones.
+    fn draw_territory(&self, ctx: &mut Context, dead_stones: &GoBoardMask) -> GameResult<()> {
+        let owners = territory(self.game.current_board(), dead_stones, self.settings.ruleset);
+        let mut overlay = graphics::MeshBuilder::new();
+        let half_side = self.grid_box_len / 6.0;
+
+        for (i, &owner) in owners.iter().enumerate() {
+            if owner.is_none() {
+                continue;
+            }
+            let c = GoCoordinates::from(i);
+            let mut color = if owner == GoStone::BLACK {BLACK_STONE_COLOR} else {WHITE_STONE_COLOR};
+            color.a = TERRITORY_ALPHA;
+
+            let (x, y) = (c.x() as usize, c.y() as usize);
+            let bounds = graphics::Rect::new(self.grid[x] - half_side, self.grid[y] - half_side, 2.0 * half_side, 2.0 * half_side);
+            overlay.rectangle(graphics::DrawMode::fill(), bounds, color)?;
+        }
+
+        if let Ok(overlay) = overlay.build(ctx) {
+            graphics::draw(ctx, &overlay, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, NO_TEINT))?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the current game by a fresh one with `settings`.
+    fn start_game(&mut self, settings: GameSettings) {
+        let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let game = GoGame::with_handicap(settings.handicap).unwrap_or_else(GoGame::new);
+        let create = |index: usize| Player::create_or_human(&settings.players[index], &self.book, settings.komi, seed + index as u64);
+        let mut players = [create(0), create(1)];
+        for player in players.iter_mut() {
+            if let Some(ai) = player.ai() {
+                ai.set_game(game.clone());
+            }
+        }
+
+        let now = Instant::now();
+        self.clock = GameClock::new(settings.time_control());
+        self.clock.start(game.current_turn(), now);
+        self.black_player_label.set_text(&format!("Black: {}", players[0].name()));
+        self.white_player_label.set_text(&format!("White: {}", players[1].name()));
+        self.move_delay = self.fixed_delay.unwrap_or(if players.iter().any(Player::is_human) {Duration::from_secs(0)} else {AI_VS_AI_DELAY});
+        self.players = players;
+        self.game = game;
+        self.paused = false;
+        self.step = false;
+        self.last_move_time = now;
+        self.settings = settings;
+        self.thinking = None;
+        self.dead_stones = None;
+        self.score = None;
+        self.result_panel.clear();
+        self.history.view(None);
+    }
+
+    /// The position on the board: the current one, or an earlier one from the move history.
+    fn shown_board(&self) -> &GoBoard {
+        match self.history.viewed() {
+            Some(moves) => &self.game.history()[moves],
+            None => self.game.current_board(),
+        }
+    }
+
+    /// The point under the window position `x`, `y`, if it is on the board.
+    fn point_at(&self, x: f32, y: f32) -> Option<GoCoordinates> {
+        let x = (x - BOARD_MARGIN) / self.grid_box_len;
+        let y = (y - BOARD_MARGIN) / self.grid_box_len;
+        if (0.0..9.0).contains(&x) && (0.0..9.0).contains(&y) {
+            Some(GoCoordinates::new_usize(x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    /// The shown position as a game that can be searched, even once the game is over.
+    fn shown_game(&self) -> GoGame {
+        let moves = self.history.viewed().unwrap_or(self.game.moves().len());
+        self.game.branch(moves).unwrap_or_else(|| self.game.clone())
+    }
+
+    fn current_player(&self) -> &Player {
+        &self.players[player_index(self.game.current_turn())]
+    }
+
+    /// Whether a human may play the next move on the board.
+    fn human_to_move(&self) -> bool {
+        self.current_player().is_human() && !self.game.is_over() && !self.paused && !self.in_scoring() && self.history.viewed().is_none()
+    }
+
+    /// Whether both players passed and the dead stones are being marked.
+    fn in_scoring(&self) -> bool {
+        self.dead_stones.is_some() && !self.game.is_over()
+    }
+
+    /// Tells the AIs about the new position after a move, and lets an AI ponder while a human thinks.
+    fn move_played(&mut self) {
+        let now = Instant::now();
+        self.clock.press(now);
+        if self.paused {
+            self.clock.stop(now);
+        }
+        self.last_move_time = now;
+
+        for player in self.players.iter_mut() {
+            if let Some(ai) = player.ai() {
+                ai.set_game(self.game.clone());
+            }
+        }
+
+        if self.game.consecutive_passes() >= 2 {
+            self.start_scoring();
+        } else {
+            self.start_pondering();
+        }
+    }
+
+    /// Lets the AIs search the current position while a human is to move in a game that goes on.
+    fn start_pondering(&mut self) {
+        if !self.current_player().is_human() || self.game.is_over() || self.in_scoring() {
+            return;
+        }
+        for player in self.players.iter_mut() {
+            if let Some(ai) = player.ai() {
+                ai.start_pondering();
+            }
+        }
+    }
+
+    /// Stops the clock and the pondering after two passes, and guesses the dead stones from playouts.
+    fn start_scoring(&mut self) {
+        self.clock.stop(Instant::now());
+        for player in self.players.iter_mut() {
+            if let Some(ai) = player.ai() {
+                ai.stop_pondering();
+            }
+        }
+        let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let ownership = estimate_ownership(&self.game, &RandomPolicy, SCORING_PLAYOUTS, SCORING_THREADS, seed);
+
+        let mut dead_stones = GoBoardMask::new();
+        for c in ownership.dead_stones(self.game.current_board()) {
+            dead_stones.set(c, true);
+        }
+        self.dead_stones = Some(dead_stones);
+    }
+
+    /// Marks the group at `c` dead, or alive again if it already was.
+    fn toggle_dead(&mut self, c: GoCoordinates) {
+        let board = self.game.current_board();
+        if let Some(dead_stones) = &mut self.dead_stones {
+            if board.get_stone(c).is_none() {
+                return;
+            }
+            let dead = !dead_stones.get(c);
+            for stone in board.group(c) {
+                dead_stones.set(stone, dead);
+            }
+        }
+    }
+
+    /// The score of the current position with the marked dead stones, counted again only when they changed.
+    fn current_score(&mut self) -> GameScore {
+        let dead_stones = self.dead_stones.clone().unwrap_or_else(GoBoardMask::new);
+        let key = (self.game.current_board().clone(), self.game.black_captures(), self.game.white_captures(), dead_stones);
+        match &self.score {
+            Some((counted, score)) if *counted == key => score.clone(),
+            _ => {
+                let (board, black_captures, white_captures, dead_stones) = &key;
+                let score = score(board, dead_stones, *black_captures, *white_captures, self.settings.komi, self.settings.ruleset);
+                self.score = Some((key, score.clone()));
+                score
+            }
+        }
+    }
+
+    /// Ends the game with the dead stones as marked, and shows how the score was counted.
+    fn finish_scoring(&mut self) {
+        let score = self.current_score();
+        self.end_game(|game| game.end_by_score(&score));
+
+        let winner = match score.winner() {
+            GoStone::BLACK => format!("Winner: Black by {}", score.margin().abs()),
+            GoStone::WHITE => format!("Winner: White by {}", score.margin().abs()),
+            GoStone::NONE => "Draw".to_string(),
+        };
+        let lines: [String; row::RESULT_LINES] = [
+            format!("Area: B {}, W {}", score.area_score(GoStone::BLACK), score.area_score(GoStone::WHITE)),
+            format!("Territory: B {}, W {}", score.territory_score(GoStone::BLACK), score.territory_score(GoStone::WHITE)),
+            format!("Captures: B {}, W {}", score.prisoners(GoStone::BLACK), score.prisoners(GoStone::WHITE)),
+            format!("Komi: {}", score.komi()),
+            winner,
+        ];
+        self.result_panel = lines.iter().enumerate().map(|(index, line)| Label::new(line, panel::position(row::RESULT + index, 0.0))).collect();
+    }
+
+    /// Leaves the scoring phase to settle disputed groups by playing on.
+    fn resume_play(&mut self) {
+        self.dead_stones = None;
+        self.last_move_time = Instant::now();
+        if !self.paused {
+            self.clock.start(self.game.current_turn(), Instant::now());
+        }
+        self.start_pondering();
+    }
+
+    /// Lets the AI to move search its move on a worker thread, so the window keeps responding.
+    fn start_thinking(&mut self) {
+        let color = self.game.current_turn();
+        let clock = self.clock.current(color, Instant::now());
+        let mut ai = match &mut self.players[player_index(color)] {
+            Player::AI {ai, ..} => match ai.take() {
+                Some(ai) => ai,
+                None => return,
+            },
+            Player::Human => return,
+        };
+
+        ai.set_time_left(&clock);
+        self.thinking = Some(thread::spawn(move || {
+            let best_move = ai.best_move();
+            (ai, best_move)
+        }));
+    }
+
+    /// Plays the move of the AI once its search is finished.
+    fn play_ai_move(&mut self) {
+        if !self.thinking.as_ref().is_some_and(JoinHandle::is_finished) {
+            return;
+        }
+        let (ai, best_move) = self.thinking.take().unwrap().join().unwrap();
+        let color = self.game.current_turn();
+        let resign = match &mut self.players[player_index(color)] {
+            Player::AI {ai: slot, resign_policy, ..} => {
+                let resign = ai.win_rate_estimate().is_some_and(|win_rate| resign_policy.should_resign(win_rate));
+                *slot = Some(ai);
+                resign
+            }
+            Player::Human => return,
+        };
+        if resign {
+            self.end_game(|game| game.resign(color));
+            return;
+        }
+
+        match self.game.play(best_move) {
+            Ok(_) => self.move_played(),
+            Err(_) => match best_move {
+                Some(coordinates) => println!("AI move {}, {} is invalid", coordinates.x(), coordinates.y()),
+                None => println!("AI pass is invalid"),
+            },
+        }
+    }
+
+    /// Takes back the last move, and the AI replies before it so a human is to move again.
+    fn undo(&mut self) {
+        if self.game.undo().is_err() {
+            println!("Could not undo");
+            return;
+        }
+        self.dead_stones = None;
+        if self.players.iter().any(Player::is_human) {
+            while !self.current_player().is_human() && self.game.undo().is_ok() {}
+        }
+        self.position_changed();
+    }
+
+    /// Continues the game from the first `moves` moves, dropping the moves after them.
+    fn continue_from(&mut self, moves: usize) {
+        match self.game.branch(moves) {
+            Some(game) => self.game = game,
+            None => return,
+        }
+        self.dead_stones = None;
+        self.result_panel.clear();
+        self.position_changed();
+        if self.game.consecutive_passes() >= 2 {
+            self.start_scoring();
+        }
+    }
+
+    /// Gives up the search of the AI to move when the game changed under it. The AI stays with
+    /// its thread until the search ends, so the player gets a new one.
+    fn cancel_thinking(&mut self) {
+        if self.thinking.take().is_none() {
+            return;
+        }
+        let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        for player in self.players.iter_mut() {
+            if let Player::AI {name, ai: None, ..} = player {
+                let mut new_player = Player::create_or_human(name, &self.book, self.settings.komi, seed);
+                if let Some(ai) = new_player.ai() {
+                    ai.set_game(self.game.clone());
+                }
+                *player = new_player;
+            }
+        }
+    }
+
+    /// Restarts the clock and the AIs after the game went back to an earlier position.
+    fn position_changed(&mut self) {
+        self.cancel_thinking();
+        let now = Instant::now();
+        if !self.paused {
+            self.clock.start(self.game.current_turn(), now);
+        }
+        self.last_move_time = now;
+        for player in self.players.iter_mut() {
+            if let Player::AI {ai: Some(ai), resign_policy, ..} = player {
+                resign_policy.reset();
+                ai.set_game(self.game.clone());
+            }
+        }
+        self.start_pondering();
+    }
+
+    fn end_game<F: FnOnce(&mut GoGame)>(&mut self, end: F) {
+        end(&mut self.game);
+        self.cancel_thinking();
+        self.clock.stop(Instant::now());
+        for player in self.players.iter_mut() {
+            if let Some(ai) = player.ai() {
+                ai.stop_pondering();
+            }
+        }
+        self.save_game();
+    }
+
+    fn save_game(&self) {
+        let mut root = from_game(&self.game, self.settings.komi);
+        root.set_property("PB", self.players[0].name());
+        root.set_property("PW", self.players[1].name());
+
+        let seconds = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let path = Path::new(GAMES_DIRECTORY).join(format!("{}.sgf", seconds));
+        if fs::create_dir_all(GAMES_DIRECTORY).and_then(|_| fs::write(&path, to_sgf(&root))).is_err() {
+            println!("Could not save the game to {}", path.display());
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if self.game.is_over() || self.in_scoring() {
+            return;
+        }
+        if self.paused {
+            self.clock.stop(Instant::now());
+        } else {
+            self.clock.start(self.game.current_turn(), Instant::now());
+        }
+    }
+}
+
+impl EventHandler for MyGame {
     fn mouse_motion_event(
         &mut self,
         _ctx: &mut Context,
@@ -88,6 +966,37 @@ impl<AI: GoAI> EventHandler for MyGame<AI> {
         self.mouse_y = y;
     }
 
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool
+    ) {
+        if self.settings_screen.is_some() && keycode != KeyCode::Escape {
+            return;
+        }
+
+        match keycode {
+            KeyCode::Escape => event::quit(ctx),
+            KeyCode::T => self.toggle_overlay(Overlay::Territory),
+            KeyCode::V => self.toggle_overlay(Overlay::Voronoi),
+            KeyCode::I => self.toggle_overlay(Overlay::Influence),
+            KeyCode::O => self.toggle_overlay(Overlay::Ownership),
+            KeyCode::M => self.show_move_numbers = !self.show_move_numbers,
+            KeyCode::A => self.toggle_analysis(),
+            KeyCode::Space => self.toggle_pause(),
+            KeyCode::N => self.step = self.paused,
+            KeyCode::Equals => self.move_delay += DELAY_STEP,
+            KeyCode::Minus => self.move_delay = self.move_delay.checked_sub(DELAY_STEP).unwrap_or_default(),
+            KeyCode::Left => self.history.step(-1),
+            KeyCode::Right => self.history.step(1),
+            KeyCode::Home => self.history.view(Some(0)),
+            KeyCode::End => self.history.view(None),
+            _ => {}
+        }
+    }
+
     fn mouse_button_up_event(
         &mut self,
         ctx: &mut Context,
@@ -95,37 +1004,128 @@ impl<AI: GoAI> EventHandler for MyGame<AI> {
         x: f32,
         y: f32
     ) {
-        if button == MouseButton::Left && x >= 0.0 && y >= 0.0 && x <= self.grid_box_len * 9.0 && y <= self.grid_box_len * 9.0 && self.game.current_turn() == GoStone::BLACK {
-            let x = ((x / self.grid_box_len).max(0.0) as usize).min(8);
-            let y = ((y / self.grid_box_len).max(0.0) as usize).min(8);
-            match self.game.play_stone(GoCoordinates::new_usize(x, y)) {
-                Ok(_) => self.ai.set_game(self.game.clone()),
-                Err(_) => println!("Could not play stone"),
+        if let Some(settings_screen) = &mut self.settings_screen {
+            settings_screen.mouse_button_up_event(ctx, button, x, y);
+            return;
+        }
+
+        if let (MouseButton::Left, Some(c)) = (button, self.point_at(x, y)) {
+            if self.history.viewed().is_some() {
+                // An earlier position is only shown, not played on.
+            } else if self.in_scoring() {
+                self.toggle_dead(c);
+            } else if self.human_to_move() {
+                match self.game.play_stone(c) {
+                    Ok(_) => self.move_played(),
+                    Err(_) => println!("Could not play stone"),
+                }
             }
         }
 
+        self.history.mouse_button_up_event(ctx, button, x, y);
         self.undo_button.mouse_button_up_event(ctx, button, x, y);
+        self.pass_button.mouse_button_up_event(ctx, button, x, y);
+        if self.in_scoring() {
+            self.done_button.mouse_button_up_event(ctx, button, x, y);
+            self.resume_button.mouse_button_up_event(ctx, button, x, y);
+        }
+        self.resign_button.mouse_button_up_event(ctx, button, x, y);
+        self.new_game_button.mouse_button_up_event(ctx, button, x, y);
     }
 
     fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+        if let Some(settings_screen) = &mut self.settings_screen {
+            match settings_screen.update() {
+                Some(SettingsResult::Start(settings)) => {
+                    if settings.save_file(SETTINGS_FILE).is_err() {
+                        println!("Could not save the settings to {}", SETTINGS_FILE);
+                    }
+                    self.settings_screen = None;
+                    self.start_game(settings);
+                }
+                Some(SettingsResult::Cancel) => {
+                    self.settings_screen = None;
+                    if self.resume_after_settings {
+                        self.toggle_pause();
+                    }
+                }
+                None => {}
+            }
+            return Ok(());
+        }
+
+        if self.new_game_button.consume_was_clicked() {
+            self.settings_screen = Some(SettingsScreen::new(self.settings.clone()));
+            self.resume_after_settings = !self.paused;
+            if !self.paused {
+                self.toggle_pause();
+            }
+            return Ok(());
+        }
+
+        if let Some(moves) = self.history.update() {
+            self.continue_from(moves);
+        }
+
         if self.undo_button.consume_was_clicked() {
-            match self.game.undo() {
-                Ok(_) => self.ai.set_game(self.game.clone()),
-                Err(_) => println!("Could not undo"),
+            self.undo();
+        }
+
+        if self.pass_button.consume_was_clicked() && self.human_to_move() && self.game.play(None).is_ok() {
+            self.move_played();
+        }
+
+        if self.done_button.consume_was_clicked() && self.in_scoring() {
+            self.finish_scoring();
+        }
+
+        if self.resume_button.consume_was_clicked() && self.in_scoring() {
+            self.resume_play();
+        }
+
+        if self.resign_button.consume_was_clicked() && self.human_to_move() {
+            let color = self.game.current_turn();
+            self.end_game(|game| game.resign(color));
+        }
+
+        if let Some(color) = self.clock.flagged(Instant::now()) {
+            if !self.game.is_over() {
+                self.end_game(|game| game.lose_on_time(color));
             }
         }
 
-        if self.game.current_turn() == GoStone::WHITE {
-            if let Some(coordinates) = self.ai.best_move() {
-                match self.game.play_stone(coordinates) {
-                    Ok(_) => self.ai.set_game(self.game.clone()),
-                    Err(_) => println!("AI move {}, {} is invalid", coordinates.x(), coordinates.y()),
-                }
+        let ai_to_move = !self.current_player().is_human() && !self.game.is_over() && !self.in_scoring();
+        if ai_to_move && self.thinking.is_none() && (!self.paused || self.step) && self.last_move_time.elapsed() >= self.move_delay {
+            if self.step {
+                self.step = false;
+                self.clock.start(self.game.current_turn(), Instant::now());
             }
+            self.start_thinking();
         }
+        self.play_ai_move();
 
+        self.history.set_game(&self.game);
         self.black_captures_label.set_text(&format!("Black captures: {}", self.game.black_captures()));
         self.white_captures_label.set_text(&format!("White captures: {}", self.game.white_captures()));
+        let score = self.current_score();
+        match self.game.result() {
+            Some(result) => self.score_label.set_text(&format!("Result: {}", result)),
+            None => self.score_label.set_text(&format!("Score: B {}, W {}", score.total(GoStone::BLACK), score.total(GoStone::WHITE))),
+        }
+        self.update_ownership();
+        self.update_analysis();
+        let analysis_text = match &self.analysis {
+            Some(analysis) => format!("[A] Analysis: {} playouts", analysis.candidates.iter().map(|candidate| candidate.visits).sum::<u32>()),
+            None => "[A] Analysis: off".to_string(),
+        };
+        self.analysis_label.set_text(&analysis_text);
+        self.overlay_label.set_text(&format!("[T/V/I/O] Overlay: {}", self.overlay.map_or("off", Overlay::name)));
+        self.move_numbers_label.set_text(if self.show_move_numbers {"[M] Move numbers: on"} else {"[M] Move numbers: off"});
+        let now = Instant::now();
+        self.black_clock_label.set_text(&format!("Black clock: {}", self.clock.current(GoStone::BLACK, now)));
+        self.white_clock_label.set_text(&format!("White clock: {}", self.clock.current(GoStone::WHITE, now)));
+        self.pause_label.set_text(if self.paused {"[Space] Resume"} else {"[Space] Pause"});
+        self.delay_label.set_text(&format!("[+/-] Delay: {} ms", self.move_delay.as_millis()));
 
         Ok(())
     }
@@ -133,6 +1133,12 @@ impl<AI: GoAI> EventHandler for MyGame<AI> {
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx, BACKGROUND_COLOR);
 
+        if let Some(settings_screen) = &self.settings_screen {
+            settings_screen.draw(ctx)?;
+            graphics::draw_queued_text(ctx, graphics::DrawParam::default(), None, graphics::FilterMode::Nearest)?;
+            return graphics::present(ctx);
+        }
+
         let mut board = graphics::MeshBuilder::new();
         for i in 0..9 {
             board.line(&[graphics::mint::Point2::from([self.grid[0], self.grid[i]]), graphics::mint::Point2::from([self.grid[8], self.grid[i]])], 2.0, BOARD_LINE_COLOR)?;
@@ -148,18 +1154,18 @@ impl<AI: GoAI> EventHandler for MyGame<AI> {
 
         let black_stone = graphics::MeshBuilder::new().circle(graphics::DrawMode::fill(), graphics::mint::Point2::from([0.0, 0.0]), self.grid_box_len / 2.0, 0.5, BLACK_STONE_COLOR)?.build(ctx)?;
         let white_stone = graphics::MeshBuilder::new().circle(graphics::DrawMode::fill(), graphics::mint::Point2::from([0.0, 0.0]), self.grid_box_len / 2.0, 0.5, WHITE_STONE_COLOR)?.build(ctx)?;
+        let human_to_move = self.human_to_move();
+        let hovered = self.point_at(self.mouse_x, self.mouse_y);
         for x in 0..9 {
             for y in 0..9 {
                 let c = GoCoordinates::new_usize(x, y);
-                match self.game.current_board().get_stone(c) {
-                    GoStone::BLACK => graphics::draw(ctx, &black_stone, (graphics::mint::Point2::from([self.grid[x], self.grid[y]]), 0.0, NO_TEINT))?,
-                    GoStone::WHITE => graphics::draw(ctx, &white_stone, (graphics::mint::Point2::from([self.grid[x], self.grid[y]]), 0.0, NO_TEINT))?,
+                let dead = self.history.viewed().is_none() && self.dead_stones.as_ref().is_some_and(|dead_stones| dead_stones.get(c));
+                let teint = if dead {GHOST_TEINT} else {NO_TEINT};
+                match self.shown_board().get_stone(c) {
+                    GoStone::BLACK => graphics::draw(ctx, &black_stone, (graphics::mint::Point2::from([self.grid[x], self.grid[y]]), 0.0, teint))?,
+                    GoStone::WHITE => graphics::draw(ctx, &white_stone, (graphics::mint::Point2::from([self.grid[x], self.grid[y]]), 0.0, teint))?,
                     GoStone::NONE => {
-                        let x_f32 = x as f32;
-                        let y_f32 = y as f32;
-
-                        if self.grid_box_len * x_f32 < self.mouse_x && self.mouse_x < self.grid_box_len * (x_f32 + 1.0) &&
-                            self.grid_box_len * y_f32 < self.mouse_y && self.mouse_y < self.grid_box_len * (y_f32 + 1.0) {
+                        if human_to_move && hovered == Some(c) && self.game.check_move(c).is_ok() {
                             match self.game.current_turn() {
                                 GoStone::BLACK => graphics::draw(ctx, &black_stone, (graphics::mint::Point2::from([self.grid[x], self.grid[y]]), 0.0, GHOST_TEINT))?,
                                 GoStone::WHITE => graphics::draw(ctx, &white_stone, (graphics::mint::Point2::from([self.grid[x], self.grid[y]]), 0.0, GHOST_TEINT))?,
@@ -171,11 +1177,55 @@ impl<AI: GoAI> EventHandler for MyGame<AI> {
             }
         }
 
+        if let (true, Some(c)) = (human_to_move, hovered) {
+            self.draw_move_preview(ctx, c)?;
+        }
+
+        self.draw_annotations(ctx)?;
+        if let Some(analysis) = &self.analysis {
+            self.draw_analysis(ctx, analysis, &black_stone, &white_stone)?;
+        }
+        if let Some(overlay) = self.overlay {
+            self.draw_overlay(ctx, overlay)?;
+        }
+        if let (Some(dead_stones), None) = (&self.dead_stones, self.history.viewed()) {
+            self.draw_territory(ctx, dead_stones)?;
+        }
+
         self.undo_button.draw(ctx)?;
+        self.pass_button.draw(ctx)?;
+        self.resign_button.draw(ctx)?;
+        self.new_game_button.draw(ctx)?;
         self.black_captures_label.draw(ctx)?;
         self.white_captures_label.draw(ctx)?;
+        self.score_label.draw(ctx)?;
+        self.overlay_label.draw(ctx)?;
+        if let Some(legend) = &self.legend {
+            legend.draw(ctx)?;
+        }
+        self.black_clock_label.draw(ctx)?;
+        self.white_clock_label.draw(ctx)?;
+        self.black_player_label.draw(ctx)?;
+        self.white_player_label.draw(ctx)?;
+        self.pause_label.draw(ctx)?;
+        self.step_label.draw(ctx)?;
+        self.delay_label.draw(ctx)?;
+        if self.in_scoring() {
+            self.done_button.draw(ctx)?;
+            self.resume_button.draw(ctx)?;
+            self.scoring_label.draw(ctx)?;
+        }
+        for label in &self.result_panel {
+            label.draw(ctx)?;
+        }
+        self.history.draw(ctx)?;
+        self.move_numbers_label.draw(ctx)?;
+        self.analysis_label.draw(ctx)?;
+        for label in &self.coordinate_labels {
+            label.draw(ctx)?;
+        }
 
         graphics::draw_queued_text(ctx, graphics::DrawParam::default(), None, graphics::FilterMode::Nearest)?;
         graphics::present(ctx)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,141 @@
+use ggez::{Context, GameResult};
+use ggez::event::MouseButton;
+use ggez::graphics;
+use go_ai::go::GoGame;
+use go_ai::go::board::GoStone;
+use crate::ui::button::Button;
+use crate::ui::label::Label;
+use crate::ui::panel;
+
+const SLIDER_WIDTH: f32 = 180.0;
+const SLIDER_HEIGHT: f32 = 10.0;
+
+/// Lists the moves of the game, and lets the player look at an earlier position with the
+/// slider, the arrow keys or by clicking a move. The earlier position can be continued as a new branch.
+pub struct HistoryPanel {
+    moves: Vec<String>,
+    /// The panel row of the title, followed by the slider, the listed moves and the continue button.
+    top: usize,
+    /// The number of moves listed at once, ending at the shown position.
+    listed: usize,
+    /// The number of moves played in the shown position, or `None` for the current one.
+    viewed: Option<usize>,
+    title: Label,
+    /// The buttons of the listed moves, with the position each one shows.
+    rows: Vec<(usize, Button)>,
+    slider_click: Option<f32>,
+    continue_button: Button,
+}
+
+impl HistoryPanel {
+    /// A panel taking `listed + 3` rows of the side panel from row `top`.
+    pub fn new(top: usize, listed: usize) -> Self {
+        Self {
+            moves: Vec::new(),
+            top,
+            listed,
+            viewed: None,
+            title: Label::new("Moves [Left/Right]", panel::position(top, 0.0)),
+            rows: Vec::new(),
+            slider_click: None,
+            continue_button: Button::new(Label::new("Continue here", panel::position(top + 2 + listed, 0.0))),
+        }
+    }
+
+    /// Updates the list after moves were played or taken back.
+    pub fn set_game(&mut self, game: &GoGame) {
+        let count = game.moves().len();
+        let moves: Vec<_> = game.moves().iter().enumerate().map(|(index, game_move)| {
+            let color = if (count - index) % 2 == 1 {game.current_turn().opponent_color()} else {game.current_turn()};
+            let name = game_move.map_or("pass".to_string(), |coordinates| coordinates.to_string());
+            format!("{}. {} {}", index + 1, if color == GoStone::BLACK {"B"} else {"W"}, name)
+        }).collect();
+
+        if moves != self.moves {
+            self.moves = moves;
+            self.view(self.viewed);
+        }
+    }
+
+    /// The number of moves in the shown position, while an earlier position is shown.
+    pub fn viewed(&self) -> Option<usize> {
+        self.viewed
+    }
+
+    /// Shows the position after `moves` moves, or the current one for `None` or the last move.
+    pub fn view(&mut self, moves: Option<usize>) {
+        self.viewed = moves.filter(|&moves| moves < self.moves.len());
+
+        let end = self.viewed.unwrap_or(self.moves.len()).max(self.listed.min(self.moves.len()));
+        let start = end.saturating_sub(self.listed);
+        self.rows = (start..end).enumerate().map(|(row, index)| {
+            let marker = if index + 1 == self.viewed.unwrap_or(self.moves.len()) {"> "} else {"  "};
+            let label = Label::new(&format!("{}{}", marker, self.moves[index]), panel::position(self.top + 2 + row, 0.0));
+            (index + 1, Button::new(label))
+        }).collect();
+    }
+
+    /// Steps `step` moves back or forward from the shown position.
+    pub fn step(&mut self, step: i32) {
+        let current = self.viewed.unwrap_or(self.moves.len()) as i32;
+        self.view(Some((current + step).max(0) as usize));
+    }
+
+    pub fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if button == MouseButton::Left && self.slider_bounds().contains([x, y]) {
+            self.slider_click = Some((x - panel::LEFT) / SLIDER_WIDTH);
+        }
+        for (_, row) in self.rows.iter_mut() {
+            row.mouse_button_up_event(ctx, button, x, y);
+        }
+        if self.viewed.is_some() {
+            self.continue_button.mouse_button_up_event(ctx, button, x, y);
+        }
+    }
+
+    /// Applies the clicks since the last update, and returns the number of moves to keep
+    /// when the player continues from the shown position.
+    pub fn update(&mut self) -> Option<usize> {
+        if let Some(fraction) = self.slider_click.take() {
+            self.view(Some((fraction * self.moves.len() as f32).round() as usize));
+        }
+        if let Some(moves) = self.rows.iter_mut().filter_map(|(moves, row)| if row.consume_was_clicked() {Some(*moves)} else {None}).last() {
+            self.view(Some(moves));
+        }
+
+        if self.continue_button.consume_was_clicked() {
+            let moves = self.viewed.take();
+            self.view(None);
+            moves
+        } else {
+            None
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        self.title.draw(ctx)?;
+
+        let bounds = self.slider_bounds();
+        let shown = self.viewed.unwrap_or(self.moves.len());
+        let fraction = if self.moves.is_empty() {1.0} else {shown as f32 / self.moves.len() as f32};
+        let slider = graphics::MeshBuilder::new()
+            .rectangle(graphics::DrawMode::stroke(1.0), bounds, graphics::Color::BLACK)?
+            .rectangle(graphics::DrawMode::fill(), graphics::Rect::new(bounds.x, bounds.y, fraction * bounds.w, bounds.h), graphics::Color::BLACK)?
+            .build(ctx)?;
+        graphics::draw(ctx, &slider, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, crate::NO_TEINT))?;
+
+        for (_, row) in &self.rows {
+            row.draw(ctx)?;
+        }
+        if self.viewed.is_some() {
+            self.continue_button.draw(ctx)?;
+        }
+        Ok(())
+    }
+
+    /// The slider, in the row below the title.
+    fn slider_bounds(&self) -> graphics::Rect {
+        let position = panel::position(self.top + 1, 0.0);
+        graphics::Rect::new(position.x, position.y + 2.0, SLIDER_WIDTH, SLIDER_HEIGHT)
+    }
+}
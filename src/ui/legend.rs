@@ -0,0 +1,45 @@
+use ggez::{Context, GameResult};
+use ggez::graphics;
+use crate::ui::label::Label;
+
+const SWATCH_SIDE: f32 = 12.0;
+const ENTRY_WIDTH: f32 = 95.0;
+/// The opacities of the swatches of a legend for a continuous scale.
+const RAMP: [f32; 3] = [0.25, 0.5, 1.0];
+
+/// Explains the colors of a board overlay with a swatch and a name per color, in one row.
+pub struct Legend {
+    entries: Vec<(graphics::Color, Label)>,
+    coordinates: graphics::mint::Point2<f32>,
+    /// Whether each swatch shows increasing opacities, for overlays where opacity means strength.
+    ramp: bool,
+}
+
+impl Legend {
+    pub fn new(entries: &[(graphics::Color, &str)], ramp: bool, coordinates: graphics::mint::Point2<f32>) -> Self {
+        let swatches = if ramp {RAMP.len()} else {1};
+        let entries = entries.iter().enumerate().map(|(index, &(color, name))| {
+            let x = coordinates.x + ENTRY_WIDTH * index as f32 + (SWATCH_SIDE + 2.0) * swatches as f32 + 2.0;
+            (color, Label::new(name, [x, coordinates.y].into()))
+        }).collect();
+        Self {entries, coordinates, ramp}
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let mut swatches = graphics::MeshBuilder::new();
+        let alphas: &[f32] = if self.ramp {&RAMP} else {&[1.0]};
+
+        for (index, (color, label)) in self.entries.iter().enumerate() {
+            for (step, &alpha) in alphas.iter().enumerate() {
+                let mut color = *color;
+                color.a = alpha;
+                let x = self.coordinates.x + ENTRY_WIDTH * index as f32 + (SWATCH_SIDE + 2.0) * step as f32;
+                swatches.rectangle(graphics::DrawMode::fill(), graphics::Rect::new(x, self.coordinates.y + 2.0, SWATCH_SIDE, SWATCH_SIDE), color)?;
+            }
+            label.draw(ctx)?;
+        }
+
+        let swatches = swatches.build(ctx)?;
+        graphics::draw(ctx, &swatches, (graphics::mint::Point2::from([0.0, 0.0]), 0.0, crate::NO_TEINT))
+    }
+}
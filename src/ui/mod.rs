@@ -1,2 +1,6 @@
 pub mod button;
+pub mod history;
 pub mod label;
+pub mod legend;
+pub mod panel;
+pub mod settings;
@@ -0,0 +1,40 @@
+use ggez::graphics::mint::Point2;
+
+/// The left edge of the side panel, right of the board.
+pub const LEFT: f32 = 610.0;
+const TOP: f32 = 10.0;
+pub const ROW_HEIGHT: f32 = 20.0;
+/// The rows that fit in the 600 pixel high window.
+pub const ROWS: usize = 29;
+
+/// The rows of the side panel, from the top. Every element of the panel takes its place from
+/// here, so an element is added or moved without shifting the others by hand.
+pub mod row {
+    pub const BUTTONS: usize = 0;
+    pub const BLACK_CAPTURES: usize = 1;
+    pub const WHITE_CAPTURES: usize = 2;
+    pub const SCORE: usize = 3;
+    pub const OVERLAY: usize = 4;
+    pub const LEGEND: usize = 5;
+    pub const BLACK_CLOCK: usize = 6;
+    pub const WHITE_CLOCK: usize = 7;
+    pub const BLACK_PLAYER: usize = 8;
+    pub const WHITE_PLAYER: usize = 9;
+    pub const PAUSE: usize = 10;
+    pub const STEP: usize = 11;
+    pub const DELAY: usize = 12;
+    /// The buttons that end scoring, replaced by the lines of the result once the game is counted.
+    pub const RESULT: usize = 13;
+    pub const RESULT_LINES: usize = 5;
+    /// Shown while scoring, so it can share the rows of the result.
+    pub const SCORING: usize = RESULT + 1;
+    pub const MOVE_NUMBERS: usize = RESULT + RESULT_LINES;
+    pub const ANALYSIS: usize = MOVE_NUMBERS + 1;
+    /// The move history takes the rest of the column.
+    pub const HISTORY: usize = ANALYSIS + 1;
+}
+
+/// The position of `row`, `offset` pixels right of the left edge of the panel.
+pub fn position(row: usize, offset: f32) -> Point2<f32> {
+    [LEFT + offset, TOP + ROW_HEIGHT * row as f32].into()
+}
@@ -0,0 +1,229 @@
+use ggez::{Context, GameResult};
+use ggez::event::MouseButton;
+use go_ai::go::ai::available_ai_names;
+use go_ai::go::clock::TimeControl;
+use go_ai::go::scoring::{Ruleset, DEFAULT_KOMI};
+use go_ai::go::HANDICAP_POINTS;
+use crate::ui::button::Button;
+use crate::ui::label::Label;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+pub const HUMAN: &str = "human";
+const KOMI_STEP: f32 = 0.5;
+const MAX_KOMI: f32 = 20.0;
+
+/// The time controls that can be chosen, by name.
+pub const TIME_CONTROLS: &[(&str, TimeControl)] = &[
+    ("none", TimeControl::Unlimited),
+    ("10 min", TimeControl::Absolute {main_time: Duration::from_secs(10 * 60)}),
+    ("10 min + 5x30 s", TimeControl::Japanese {main_time: Duration::from_secs(10 * 60), period_time: Duration::from_secs(30), periods: 5}),
+    ("5 min + 10/2 min", TimeControl::Canadian {main_time: Duration::from_secs(5 * 60), period_time: Duration::from_secs(2 * 60), stones: 10}),
+    ("5 min + 10 s", TimeControl::Fischer {main_time: Duration::from_secs(5 * 60), increment: Duration::from_secs(10)}),
+];
+
+/// The choices for a new game, saved as `key value` lines between runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameSettings {
+    pub ruleset: Ruleset,
+    pub komi: f32,
+    pub handicap: usize,
+    /// An index into `TIME_CONTROLS`.
+    pub time_control: usize,
+    /// `HUMAN` or one of `available_ai_names` for black and white.
+    pub players: [String; 2],
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Setting {
+    Ruleset,
+    Komi,
+    Handicap,
+    TimeControl,
+    Black,
+    White,
+}
+
+const SETTINGS: [Setting; 6] = [Setting::Ruleset, Setting::Komi, Setting::Handicap, Setting::TimeControl, Setting::Black, Setting::White];
+
+/// The choices for a player: a human or one of the AIs that can play.
+fn player_names() -> Vec<&'static str> {
+    std::iter::once(HUMAN).chain(available_ai_names()).collect()
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Moves `step` places from `current` in a list of `len` choices, wrapping around.
+fn cycle(current: usize, len: usize, step: i32) -> usize {
+    (current as i32 + step).rem_euclid(len as i32) as usize
+}
+
+impl GameSettings {
+    pub fn new() -> Self {
+        Self {ruleset: Ruleset::Japanese, komi: DEFAULT_KOMI, handicap: 0, time_control: 2, players: [HUMAN.to_string(), "voronoi".to_string()]}
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        TIME_CONTROLS[self.time_control].1
+    }
+
+    /// Loads the settings saved by `save_file`. Unknown lines and invalid values keep their defaults.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut result = Self::new();
+
+        for line in text.lines() {
+            let mut parts = line.splitn(2, ' ');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _ => continue,
+            };
+
+            match key {
+                "ruleset" if value == "japanese" => result.ruleset = Ruleset::Japanese,
+                "ruleset" if value == "chinese" => result.ruleset = Ruleset::Chinese,
+                "komi" => result.komi = value.parse().ok().filter(|komi: &f32| komi.is_finite()).map_or(result.komi, |komi| komi.clamp(-MAX_KOMI, MAX_KOMI)),
+                "handicap" => result.handicap = value.parse().ok().filter(|&stones| stones <= HANDICAP_POINTS.len()).unwrap_or(result.handicap),
+                "time" => result.time_control = TIME_CONTROLS.iter().position(|(name, _)| *name == value).unwrap_or(result.time_control),
+                "black" | "white" if player_names().contains(&value) => result.players[if key == "black" {0} else {1}] = value.to_string(),
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "ruleset {}", if self.ruleset == Ruleset::Japanese {"japanese"} else {"chinese"})?;
+        writeln!(writer, "komi {}", self.komi)?;
+        writeln!(writer, "handicap {}", self.handicap)?;
+        writeln!(writer, "time {}", TIME_CONTROLS[self.time_control].0)?;
+        writeln!(writer, "black {}", self.players[0])?;
+        writeln!(writer, "white {}", self.players[1])?;
+        writer.flush()
+    }
+
+    fn change(&mut self, setting: Setting, step: i32) {
+        match setting {
+            Setting::Ruleset => self.ruleset = if self.ruleset == Ruleset::Japanese {Ruleset::Chinese} else {Ruleset::Japanese},
+            Setting::Komi => self.komi = (self.komi + step as f32 * KOMI_STEP).clamp(-MAX_KOMI, MAX_KOMI),
+            // A single handicap stone is the same as none, so it is skipped.
+            Setting::Handicap => {
+                self.handicap = cycle(self.handicap, HANDICAP_POINTS.len() + 1, step);
+                if self.handicap == 1 {
+                    self.handicap = cycle(1, HANDICAP_POINTS.len() + 1, step);
+                }
+            }
+            Setting::TimeControl => self.time_control = cycle(self.time_control, TIME_CONTROLS.len(), step),
+            Setting::Black | Setting::White => {
+                let index = if setting == Setting::Black {0} else {1};
+                let names = player_names();
+                let current = names.iter().position(|&name| name == self.players[index]).unwrap_or(0);
+                self.players[index] = names[cycle(current, names.len(), step)].to_string();
+            }
+        }
+    }
+
+    fn describe(&self, setting: Setting) -> String {
+        match setting {
+            Setting::Ruleset => format!("Rules: {}", if self.ruleset == Ruleset::Japanese {"Japanese"} else {"Chinese"}),
+            Setting::Komi => format!("Komi: {}", self.komi),
+            Setting::Handicap => format!("Handicap: {}", self.handicap),
+            Setting::TimeControl => format!("Time: {}", TIME_CONTROLS[self.time_control].0),
+            Setting::Black => format!("Black: {}", self.players[0]),
+            Setting::White => format!("White: {}", self.players[1]),
+        }
+    }
+}
+
+pub enum SettingsResult {
+    Start(GameSettings),
+    Cancel,
+}
+
+struct SettingRow {
+    setting: Setting,
+    label: Label,
+    previous: Button,
+    next: Button,
+}
+
+/// Lets the player change the settings of a new game with a `<` and `>` button per setting.
+/// The board size is not a setting: only 9x9 is supported.
+pub struct SettingsScreen {
+    settings: GameSettings,
+    title: Label,
+    board_size: Label,
+    rows: Vec<SettingRow>,
+    start_button: Button,
+    cancel_button: Button,
+}
+
+impl SettingsScreen {
+    pub fn new(settings: GameSettings) -> Self {
+        let rows = SETTINGS.iter().enumerate().map(|(index, &setting)| {
+            let y = 110.0 + 30.0 * index as f32;
+            SettingRow {
+                setting,
+                label: Label::new(&settings.describe(setting), [240.0, y].into()),
+                previous: Button::new(Label::new("<", [210.0, y].into())),
+                next: Button::new(Label::new(">", [480.0, y].into())),
+            }
+        }).collect();
+
+        Self {
+            settings,
+            title: Label::new("New game", [240.0, 40.0].into()),
+            board_size: Label::new("Board size: 9x9 (the only size supported)", [240.0, 80.0].into()),
+            rows,
+            start_button: Button::new(Label::new("Start", [240.0, 310.0].into())),
+            cancel_button: Button::new(Label::new("Cancel", [300.0, 310.0].into())),
+        }
+    }
+
+    pub fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        for row in self.rows.iter_mut() {
+            row.previous.mouse_button_up_event(ctx, button, x, y);
+            row.next.mouse_button_up_event(ctx, button, x, y);
+        }
+        self.start_button.mouse_button_up_event(ctx, button, x, y);
+        self.cancel_button.mouse_button_up_event(ctx, button, x, y);
+    }
+
+    /// Applies the clicks since the last update, and returns the choice once the screen is closed.
+    pub fn update(&mut self) -> Option<SettingsResult> {
+        for row in self.rows.iter_mut() {
+            let step = row.next.consume_was_clicked() as i32 - row.previous.consume_was_clicked() as i32;
+            if step != 0 {
+                self.settings.change(row.setting, step);
+                row.label.set_text(&self.settings.describe(row.setting));
+            }
+        }
+
+        if self.start_button.consume_was_clicked() {
+            Some(SettingsResult::Start(self.settings.clone()))
+        } else if self.cancel_button.consume_was_clicked() {
+            Some(SettingsResult::Cancel)
+        } else {
+            None
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        self.title.draw(ctx)?;
+        self.board_size.draw(ctx)?;
+        for row in &self.rows {
+            row.label.draw(ctx)?;
+            row.previous.draw(ctx)?;
+            row.next.draw(ctx)?;
+        }
+        self.start_button.draw(ctx)?;
+        self.cancel_button.draw(ctx)
+    }
+}